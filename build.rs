@@ -0,0 +1,22 @@
+use std::env;
+use std::path::Path;
+use wayland_scanner::{generate_code, Side};
+
+/// Protocols that are not yet provided by the `wayland-protocols` crate,
+/// along with the path of their definitions relative to the crate root.
+const PROTOCOLS: &[(&str, &str)] = &[
+    ("fractional_scale_v1", "protocols/fractional-scale-v1.xml"),
+];
+
+fn main() {
+    let out_dir = env::var("OUT_DIR").unwrap();
+
+    for (name, path) in PROTOCOLS {
+        println!("cargo:rerun-if-changed={}", path);
+        generate_code(
+            path,
+            Path::new(&out_dir).join(format!("{}_client_api.rs", name)),
+            Side::Client,
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! Validation of a configuration file without starting the bar.
+
+use std::fmt::{Display, Formatter};
+use crate::config::{self, Bar, Config, StyleRef, WidgetConfig};
+use crate::modules::ModuleRegistry;
+use crate::theme::Theme;
+use crate::widgets;
+
+/// A problem found while validating a configuration file.
+pub struct Problem {
+    /// The zero-based line and column at which the problem is located, if known.
+    pub position: Option<(usize, usize)>,
+    pub msg: String,
+}
+
+impl Problem {
+    fn new<T: Into<String>>(msg: T) -> Self {
+        Problem { position: None, msg: msg.into() }
+    }
+
+    fn from_toml(err: toml::de::Error) -> Self {
+        let position = err.line_col();
+        let mut msg = err.to_string();
+
+        // The position is reported separately, so strip it from the message
+        if let Some((line, column)) = position {
+            let suffix = format!(" at line {} column {}", line + 1, column + 1);
+            if msg.ends_with(&suffix) {
+                msg.truncate(msg.len() - suffix.len());
+            }
+        }
+
+        Problem { position, msg }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some((line, column)) => write!(f, "{}:{}: {}", line + 1, column + 1, self.msg),
+            None => write!(f, "{}", self.msg),
+        }
+    }
+}
+
+/// Parses the given configuration source and resolves every widget and style
+/// in it against the registry and theme, returning all problems that were found.
+pub fn check(src: &str, registry: &ModuleRegistry) -> Vec<Problem> {
+    let config: Config = match config::parse(src) {
+        Ok(config) => config,
+        Err(err) => return vec![Problem::from_toml(err)],
+    };
+
+    let mut problems = Vec::new();
+    let theme = Theme::new(&config.theme, &Bar::default());
+    let mut styles = theme.styles().collect::<Vec<_>>();
+    styles.sort_unstable();
+    for name in styles {
+        if let Err(err) = theme.with_style(&StyleRef::Named(name.to_owned())) {
+            let msg = err.msg.unwrap_or_else(|| err.to_string());
+            problems.push(Problem::new(format!("theme.style.{}: {}", name, msg)));
+        }
+    }
+
+    for (i, bar) in config.bars.iter().enumerate() {
+        for (group, widgets) in [("left", &bar.left), ("center", &bar.center), ("right", &bar.right)] {
+            check_widgets(&format!("bar[{}].{}", i, group), widgets, registry, &theme, &mut problems);
+        }
+
+        for (output, output_config) in &bar.outputs {
+            let groups = [
+                ("left", &output_config.left),
+                ("center", &output_config.center),
+                ("right", &output_config.right),
+            ];
+            for (group, widgets) in groups {
+                if let Some(widgets) = widgets {
+                    let key = format!("bar[{}].output.\"{}\".{}", i, output, group);
+                    check_widgets(&key, widgets, registry, &theme, &mut problems);
+                }
+            }
+        }
+    }
+
+    problems
+}
+
+/// Checks that every widget exists, accepts its configuration and refers to a valid style,
+/// along with the children of containers. `key` is the configuration key under which
+/// the widgets are found, and `theme` the theme that their styles apply on top of.
+fn check_widgets(
+    key: &str,
+    widgets: &[WidgetConfig],
+    registry: &ModuleRegistry,
+    theme: &Theme,
+    problems: &mut Vec<Problem>,
+) {
+    for (i, config) in widgets.iter().enumerate() {
+        let key = format!("{}[{}]", key, i);
+        let is_container = config.widget == "container";
+
+        let mut errors = Vec::new();
+        match widgets::build_checked(config, registry) {
+            // The warnings of containers concern their children, which are checked below
+            Ok((_, Some(warning))) if !is_container => errors.push(warning),
+            Ok(_) => {}
+            Err(err) => errors.push(err),
+        }
+
+        let styled = match config.style.as_ref().map(|style| theme.with_style(style)) {
+            Some(Ok(styled)) => Some(styled),
+            Some(Err(err)) => {
+                errors.push(err);
+                None
+            }
+            None => None,
+        };
+
+        for err in errors {
+            let msg = err.msg.unwrap_or_else(|| err.to_string());
+            problems.push(Problem::new(format!("{}: {}", key, msg)));
+        }
+
+        let children = config.options.get("children")
+            .filter(|_| is_container)
+            .and_then(|children| children.clone().try_into::<Vec<WidgetConfig>>().ok());
+        if let Some(children) = children {
+            let theme = styled.as_ref().unwrap_or(theme);
+            check_widgets(&format!("{}.children", key), &children, registry, theme, problems);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Parsing of the colors used throughout the configuration.
+//!
+//! Colors are written as strings and may take any of the following forms:
+//! - `#rrggbb` or `#rrggbbaa`
+//! - `rgb(r, g, b)` or `rgba(r, g, b, a)`, where each component is
+//!   either a decimal (`255`) or a hexadecimal (`0xFF`) number.
+//!   The function names are case insensitive.
+//! - A named color, such as `black`, `white` or `transparent`.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use serde::{de, Deserialize, Deserializer};
+
+/// An 8-bit per channel RGBA color.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(0xFF, 0xFF, 0xFF);
+    pub const TRANSPARENT: Color = Color::rgba(0, 0, 0, 0);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Color { r, g, b, a: 0xFF }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Color { r, g, b, a }
+    }
+
+    fn named(name: &str) -> Option<Self> {
+        Some(match name {
+            "black" => Color::BLACK,
+            "white" => Color::WHITE,
+            "transparent" => Color::TRANSPARENT,
+            "red" => Color::rgb(0xFF, 0, 0),
+            "green" => Color::rgb(0, 0x80, 0),
+            "lime" => Color::rgb(0, 0xFF, 0),
+            "blue" => Color::rgb(0, 0, 0xFF),
+            "yellow" => Color::rgb(0xFF, 0xFF, 0),
+            "cyan" | "aqua" => Color::rgb(0, 0xFF, 0xFF),
+            "magenta" | "fuchsia" => Color::rgb(0xFF, 0, 0xFF),
+            "gray" | "grey" => Color::rgb(0x80, 0x80, 0x80),
+            "silver" => Color::rgb(0xC0, 0xC0, 0xC0),
+            "maroon" => Color::rgb(0x80, 0, 0),
+            "olive" => Color::rgb(0x80, 0x80, 0),
+            "navy" => Color::rgb(0, 0, 0x80),
+            "purple" => Color::rgb(0x80, 0, 0x80),
+            "teal" => Color::rgb(0, 0x80, 0x80),
+            "orange" => Color::rgb(0xFF, 0xA5, 0),
+            _ => return None,
+        })
+    }
+
+    fn parse_hex(digits: &str) -> Result<Self, String> {
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("`#{}` contains non-hexadecimal digits", digits));
+        }
+
+        let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).unwrap();
+        match digits.len() {
+            6 => Ok(Color::rgb(channel(0), channel(2), channel(4))),
+            8 => Ok(Color::rgba(channel(0), channel(2), channel(4), channel(6))),
+            _ => Err(format!("`#{}` must have either 6 or 8 hexadecimal digits", digits)),
+        }
+    }
+
+    fn parse_component(component: &str) -> Result<u8, String> {
+        let component = component.trim();
+        let value = match component.strip_prefix("0x").or_else(|| component.strip_prefix("0X")) {
+            Some(hex) => u8::from_str_radix(hex, 16),
+            None => component.parse::<u8>(),
+        };
+        value.map_err(|_| format!("`{}` is not a color component in the range 0-255", component))
+    }
+
+    fn parse_function(name: &str, args: &str) -> Result<Self, String> {
+        let components = args.split(',')
+            .map(Self::parse_component)
+            .collect::<Result<Vec<u8>, String>>()?;
+
+        match (name, components.as_slice()) {
+            ("rgb", &[r, g, b]) => Ok(Color::rgb(r, g, b)),
+            ("rgba", &[r, g, b, a]) => Ok(Color::rgba(r, g, b, a)),
+            ("rgb", _) => Err(format!("`rgb` expects 3 components, found {}", components.len())),
+            ("rgba", _) => Err(format!("`rgba` expects 4 components, found {}", components.len())),
+            _ => Err(format!("unknown color function `{}`", name)),
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if let Some(digits) = s.strip_prefix('#') {
+            return Self::parse_hex(digits);
+        }
+
+        if let (Some(open), true) = (s.find('('), s.ends_with(')')) {
+            let name = s[..open].trim().to_ascii_lowercase();
+            return Self::parse_function(&name, &s[open + 1..s.len() - 1]);
+        }
+
+        Self::named(&s.to_ascii_lowercase())
+            .ok_or_else(|| format!("`{}` is not a valid color", s))
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}{:02x}", self.r, self.g, self.b, self.a)
+    }
+}
+
+impl From<Color> for piet::Color {
+    fn from(color: Color) -> Self {
+        piet::Color::rgba8(color.r, color.g, color.b, color.a)
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let src = String::deserialize(deserializer)?;
+        src.parse()
+            .map_err(|err| de::Error::custom(format!("invalid color: {}", err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Color, String> {
+        s.parse()
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(parse("#FF8000"), Ok(Color::rgb(0xFF, 0x80, 0)));
+        assert_eq!(parse("#ff800080"), Ok(Color::rgba(0xFF, 0x80, 0, 0x80)));
+        assert_eq!(parse("  #000000 "), Ok(Color::BLACK));
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(parse("rgb(255, 128, 0)"), Ok(Color::rgb(0xFF, 0x80, 0)));
+        assert_eq!(parse("rgba(0xFF, 0X80, 0, 128)"), Ok(Color::rgba(0xFF, 0x80, 0, 0x80)));
+        assert_eq!(parse("RGBA(0xFF, 0xFF, 0xFF, 255)"), Ok(Color::WHITE));
+        assert_eq!(parse("Rgb (1,2,3)"), Ok(Color::rgb(1, 2, 3)));
+    }
+
+    #[test]
+    fn named() {
+        assert_eq!(parse("white"), Ok(Color::WHITE));
+        assert_eq!(parse("Transparent"), Ok(Color::TRANSPARENT));
+        assert_eq!(parse("grey"), parse("gray"));
+        assert_eq!(parse("orange"), Ok(Color::rgb(0xFF, 0xA5, 0)));
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let color = Color::rgba(0x12, 0xAB, 0, 0x7F);
+        assert_eq!(color.to_string(), "#12ab007f");
+        assert_eq!(parse(&color.to_string()), Ok(color));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse("#12345"), Err("`#12345` must have either 6 or 8 hexadecimal digits".to_owned()));
+        assert_eq!(parse("#12345g"), Err("`#12345g` contains non-hexadecimal digits".to_owned()));
+        assert_eq!(parse("rgb(1, 2)"), Err("`rgb` expects 3 components, found 2".to_owned()));
+        assert_eq!(parse("rgba(1, 2, 3)"), Err("`rgba` expects 4 components, found 3".to_owned()));
+        assert_eq!(parse("rgb(1, 2, 256)"), Err("`256` is not a color component in the range 0-255".to_owned()));
+        assert_eq!(parse("rgb(1, 2, 0xZZ)"), Err("`0xZZ` is not a color component in the range 0-255".to_owned()));
+        assert_eq!(parse("hsl(1, 2, 3)"), Err("unknown color function `hsl`".to_owned()));
+        assert_eq!(parse("chartreuse"), Err("`chartreuse` is not a valid color".to_owned()));
+    }
+
+    #[test]
+    fn deserialize_reports_invalid_colors() {
+        #[derive(Deserialize, Debug)]
+        struct Table {
+            #[allow(dead_code)]
+            color: Color,
+        }
+
+        let err = toml::from_str::<Table>("color = \"#12\"").unwrap_err().to_string();
+        assert!(err.starts_with("invalid color: `#12` must have either 6 or 8 hexadecimal digits"), "{}", err);
+    }
+}
@@ -0,0 +1,458 @@
+use smithay_client_toolkit::reexports::protocols::wlr::unstable::layer_shell::v1::client as wlr_client;
+use wlr_client::zwlr_layer_shell_v1::Layer as WlLayer;
+use wlr_client::zwlr_layer_surface_v1::{Anchor as WlAnchor, KeyboardInteractivity as WlKeyboardInteractivity};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::str::FromStr;
+use log::warn;
+use serde::{de, Deserialize, Deserializer};
+use toml::value::Table;
+use crate::error::Result;
+
+mod check;
+mod color;
+mod path;
+mod theme;
+mod watch;
+mod widget;
+
+pub use check::{check, Problem};
+pub use color::Color;
+pub use path::{expand_home, locate, search_paths};
+pub use theme::{StyleConfig, StyleRef, ThemeConfig};
+pub use watch::{watch_file, watch_signal};
+pub use widget::{Placement, WidgetConfig};
+
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum Anchor {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Anchor {
+    const VERTICAL: WlAnchor = WlAnchor::from_bits_truncate(WlAnchor::Top.bits() | WlAnchor::Bottom.bits());
+    const HORIZONTAL: WlAnchor = WlAnchor::from_bits_truncate(WlAnchor::Left.bits() | WlAnchor::Right.bits());
+
+    /// Whether a bar with this anchor stretches horizontally along its edge of the output.
+    pub fn is_horizontal(&self) -> bool {
+        matches!(self, Anchor::Top | Anchor::Bottom)
+    }
+}
+
+impl From<Anchor> for WlAnchor {
+    fn from(value: Anchor) -> Self {
+        match value {
+            Anchor::Top => Anchor::HORIZONTAL | WlAnchor::Top,
+            Anchor::Bottom => Anchor::HORIZONTAL | WlAnchor::Bottom,
+            Anchor::Left => Anchor::VERTICAL | WlAnchor::Left,
+            Anchor::Right => Anchor::VERTICAL | WlAnchor::Right,
+        }
+    }
+}
+
+/// The layer of the layer shell on which a bar is placed.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum Layer {
+    Background,
+    Bottom,
+    Top,
+    Overlay,
+}
+
+impl From<Layer> for WlLayer {
+    fn from(value: Layer) -> Self {
+        match value {
+            Layer::Background => WlLayer::Background,
+            Layer::Bottom => WlLayer::Bottom,
+            Layer::Top => WlLayer::Top,
+            Layer::Overlay => WlLayer::Overlay,
+        }
+    }
+}
+
+/// Whether, and when, a bar may receive keyboard focus.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum KeyboardInteractivity {
+    None,
+    Exclusive,
+    OnDemand,
+}
+
+impl From<KeyboardInteractivity> for WlKeyboardInteractivity {
+    fn from(value: KeyboardInteractivity) -> Self {
+        match value {
+            KeyboardInteractivity::None => WlKeyboardInteractivity::None,
+            KeyboardInteractivity::Exclusive => WlKeyboardInteractivity::Exclusive,
+            KeyboardInteractivity::OnDemand => WlKeyboardInteractivity::OnDemand,
+        }
+    }
+}
+
+/// What happens to the widgets of a bar that runs out of space.
+/// Either way, widgets of the lowest priority are affected first.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum Overflow {
+    /// Widgets are shortened down to their minimum width, and hidden if that does not suffice.
+    Ellipsize,
+    /// Widgets are hidden.
+    Hide,
+}
+
+/// The distance between a bar and the edges of the output, written as
+/// `margin = { top = 4, left = 8 }`. Edges that are left out default to 0.
+#[derive(Deserialize, Debug, Default, Copy, Clone, PartialEq)]
+#[serde(default)]
+pub struct Margin {
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+    pub left: i32,
+}
+
+/// A reference to a field exposed by a module through `Module::write`,
+/// written as `module.field` in format strings.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FieldRef {
+    pub module: String,
+    pub field: String,
+}
+
+impl FromStr for FieldRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.split_once('.') {
+            Some((module, field)) if !module.is_empty() && !field.is_empty() => Ok(FieldRef {
+                module: module.to_owned(),
+                field: field.to_owned(),
+            }),
+            _ => Err(format!("`{}` is not of the form `module.field`", s)),
+        }
+    }
+}
+
+impl Display for FieldRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.module, self.field)
+    }
+}
+
+impl<'de> Deserialize<'de> for FieldRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let src = String::deserialize(deserializer)?;
+        src.parse().map_err(de::Error::custom)
+    }
+}
+
+/// The top level of the configuration file.
+#[derive(Deserialize, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The bars shown on every output, written as an array of `[[bar]]` tables.
+    #[serde(rename = "bar")]
+    pub bars: Vec<Bar>,
+    pub theme: ThemeConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config { bars: vec![Bar { center: default_center(), ..Bar::default() }], theme: ThemeConfig::default() }
+    }
+}
+
+/// The widgets in the middle of the bar shown when no configuration file is found.
+/// The sway module is always registered, unlike modules that depend on hardware.
+fn default_center() -> Vec<WidgetConfig> {
+    vec![WidgetConfig::text("{core:sway.focused_view_name|\"desktop\"}")]
+}
+
+/// Configuration of a single bar. Any option that is left out
+/// takes on the value given by `Bar::default`.
+#[derive(Deserialize, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Bar {
+    pub anchor: Anchor,
+    pub layer: Layer,
+    /// The thickness of bars anchored to the top or bottom, otherwise their length.
+    /// See `Bar::size` for the defaults.
+    pub height: Option<u32>,
+    /// The length of bars anchored to the top or bottom, otherwise their thickness.
+    /// See `Bar::size` for the defaults.
+    pub width: Option<u32>,
+    pub margin: Margin,
+    /// Overrides the space reserved for the bar, which otherwise follows its size.
+    /// A value of 0 lets other surfaces overlap the bar, whereas -1 also keeps the
+    /// bar from being moved by the exclusive zones of other surfaces.
+    #[serde(rename = "exclusive_zone")]
+    pub exclusive_zone_override: Option<i32>,
+    /// The namespace of the layer surface, which compositors may use in their rules.
+    pub namespace: String,
+    pub keyboard_interactivity: KeyboardInteractivity,
+    /// Turns text 90° on bars anchored to the left or right of an output,
+    /// such that it reads from top to bottom.
+    pub rotate_text: bool,
+    /// Renders at the fractional scale preferred by the compositor if it supports
+    /// `wp_fractional_scale_v1`, rather than at the integer scale of the output.
+    pub fractional_scale: bool,
+    /// Overrides the foreground color of the theme.
+    pub foreground: Option<Color>,
+    /// Overrides the background color of the theme.
+    pub background: Option<Color>,
+    /// The widgets at the start of the bar, in order.
+    pub left: Vec<WidgetConfig>,
+    /// The widgets in the middle of the bar, in order.
+    pub center: Vec<WidgetConfig>,
+    /// The widgets at the end of the bar, in order.
+    pub right: Vec<WidgetConfig>,
+    /// The space between adjacent widgets, as well as between widgets and separators.
+    pub spacing: f64,
+    /// The space between the ends of the bar and the left and right groups.
+    pub padding: f64,
+    /// Text drawn between adjacent widgets of a group, such as `"|"`.
+    pub separator: Option<String>,
+    pub overflow: Overflow,
+    /// Overrides for specific outputs, keyed by output name, make or model.
+    #[serde(rename = "output")]
+    pub outputs: HashMap<String, OutputConfig>,
+}
+
+/// Options that override those of a bar on specific outputs, written as
+/// `[bar.output."DP-1"]`. Any option that is left out is inherited from the bar.
+#[derive(Deserialize, Clone, PartialEq, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    /// Setting this to `false` disables the bar on matching outputs.
+    pub enabled: Option<bool>,
+    pub anchor: Option<Anchor>,
+    pub height: Option<u32>,
+    pub width: Option<u32>,
+    pub foreground: Option<Color>,
+    pub background: Option<Color>,
+    pub left: Option<Vec<WidgetConfig>>,
+    pub center: Option<Vec<WidgetConfig>>,
+    pub right: Option<Vec<WidgetConfig>>,
+}
+
+impl OutputConfig {
+    fn apply(&self, bar: &mut Bar) {
+        if let Some(anchor) = self.anchor { bar.anchor = anchor; }
+        if let Some(height) = self.height { bar.height = Some(height); }
+        if let Some(width) = self.width { bar.width = Some(width); }
+        if let Some(foreground) = self.foreground { bar.foreground = Some(foreground); }
+        if let Some(background) = self.background { bar.background = Some(background); }
+        if let Some(left) = &self.left { bar.left = left.clone(); }
+        if let Some(center) = &self.center { bar.center = center.clone(); }
+        if let Some(right) = &self.right { bar.right = right.clone(); }
+    }
+}
+
+impl Bar {
+    /// The thickness of a bar whose thickness is not configured.
+    pub const DEFAULT_THICKNESS: u32 = 30;
+
+    /// The size requested for the bar as `(width, height)`. Unless configured otherwise,
+    /// the length of the bar is 0, which lets the compositor stretch the bar across
+    /// the output, while its thickness is `Bar::DEFAULT_THICKNESS`.
+    pub fn size(&self) -> (u32, u32) {
+        if self.anchor.is_horizontal() {
+            (self.width.unwrap_or(0), self.height.unwrap_or(Self::DEFAULT_THICKNESS))
+        } else {
+            (self.width.unwrap_or(Self::DEFAULT_THICKNESS), self.height.unwrap_or(0))
+        }
+    }
+
+    /// Resolves the configuration of the bar on the output with the given name, make and model.
+    /// Returns `None` if the bar has been disabled on the output.
+    ///
+    /// Output sections are matched against the make, model and name of the output,
+    /// with later matches taking precedence over earlier ones.
+    pub fn for_output(&self, name: &str, make: &str, model: &str) -> Option<Bar> {
+        let mut bar = Bar { outputs: HashMap::new(), ..self.clone() };
+        let mut enabled = true;
+
+        for key in [make, model, name] {
+            if key.is_empty() {
+                continue;
+            }
+            if let Some(output) = self.outputs.get(key) {
+                enabled = output.enabled.unwrap_or(enabled);
+                output.apply(&mut bar);
+            }
+        }
+
+        if enabled { Some(bar) } else { None }
+    }
+
+    /// The space that the compositor should reserve for the bar,
+    /// measured perpendicular to the edge that it is anchored to.
+    pub fn exclusive_zone(&self) -> i32 {
+        match self.exclusive_zone_override {
+            Some(zone) => zone,
+            None if self.anchor.is_horizontal() => self.size().1 as i32,
+            None => self.size().0 as i32,
+        }
+    }
+}
+
+impl Default for Bar {
+    fn default() -> Self {
+        Bar {
+            anchor: Anchor::Top,
+            layer: Layer::Top,
+            height: None,
+            width: None,
+            margin: Margin::default(),
+            exclusive_zone_override: None,
+            namespace: "owl-rs".to_owned(),
+            keyboard_interactivity: KeyboardInteractivity::None,
+            rotate_text: false,
+            fractional_scale: true,
+            foreground: None,
+            background: None,
+            left: Vec::new(),
+            center: Vec::new(),
+            right: Vec::new(),
+            spacing: 10.0,
+            padding: 10.0,
+            separator: None,
+            overflow: Overflow::Ellipsize,
+            outputs: HashMap::new(),
+        }
+    }
+}
+
+/// Parses a configuration file. Files that configure a single bar at the top level,
+/// as was done before bars moved into `[[bar]]` tables, are read as that bar.
+pub fn parse(src: &str) -> std::result::Result<Config, toml::de::Error> {
+    let err = match toml::from_str::<Config>(src) {
+        Ok(config) => return Ok(config),
+        Err(err) => err,
+    };
+
+    let table = match toml::from_str::<Table>(src) {
+        Ok(table) if !table.contains_key("bar") && !table.contains_key("theme") => table,
+        _ => return Err(err),
+    };
+
+    let mut bar = toml::from_str::<Bar>(src)?;
+    // Top-level bars used to show the default widget unless they configured their own
+    if !table.contains_key("center") {
+        bar.center = default_center();
+    }
+    warn!("Bar options at the top level of the configuration are deprecated, move them into a [[bar]] table.");
+    Ok(Config { bars: vec![bar], ..Config::default() })
+}
+
+/// Reads and parses the configuration stored at the given path.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Config> {
+    let src = std::fs::read_to_string(path)?;
+    Ok(parse(&src)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bar(src: &str) -> Bar {
+        toml::from_str(src).unwrap()
+    }
+
+    #[test]
+    fn outputs_are_matched_by_make_then_model_then_name() {
+        let bar = bar(r#"
+            [output.Dell]
+            anchor = "Bottom"
+            height = 20
+            width = 100
+
+            [output.U2720Q]
+            height = 30
+            width = 200
+
+            [output."DP-1"]
+            height = 40
+        "#);
+
+        let resolved = bar.for_output("DP-1", "Dell", "U2720Q").unwrap();
+        assert_eq!(resolved.anchor, Anchor::Bottom);
+        assert_eq!(resolved.height, Some(40));
+        assert_eq!(resolved.width, Some(200));
+        assert!(resolved.outputs.is_empty());
+
+        let resolved = bar.for_output("DP-2", "Dell", "P2419H").unwrap();
+        assert_eq!((resolved.height, resolved.width), (Some(20), Some(100)));
+
+        let resolved = bar.for_output("HDMI-A-1", "LG", "27GL850").unwrap();
+        assert_eq!(resolved.anchor, Anchor::Top);
+        assert_eq!((resolved.height, resolved.width), (None, None));
+    }
+
+    #[test]
+    fn bars_may_be_disabled_per_output() {
+        let bar = bar(r#"
+            [output.Dell]
+            enabled = false
+
+            [output."DP-1"]
+            enabled = true
+
+            [output."DP-3"]
+            enabled = false
+        "#);
+
+        assert!(bar.for_output("DP-2", "Dell", "U2720Q").is_none());
+        assert!(bar.for_output("DP-1", "Dell", "U2720Q").is_some());
+        assert!(bar.for_output("DP-3", "LG", "27GL850").is_none());
+        assert!(bar.for_output("DP-4", "LG", "27GL850").is_some());
+    }
+
+    #[test]
+    fn empty_make_and_model_match_nothing() {
+        // toml 0.5 rejects empty keys, so the output is added directly
+        let mut bar = bar(r#"
+            [output."DP-1"]
+            width = 50
+        "#);
+        bar.outputs.insert(String::new(), OutputConfig { height: Some(10), ..OutputConfig::default() });
+
+        let resolved = bar.for_output("DP-1", "", "").unwrap();
+        assert_eq!((resolved.height, resolved.width), (None, Some(50)));
+
+        let resolved = bar.for_output("", "", "").unwrap();
+        assert_eq!((resolved.height, resolved.width), (None, None));
+    }
+
+    #[test]
+    fn top_level_bar_options_configure_a_single_bar() {
+        let config = parse("anchor = \"Bottom\"\nheight = 24").unwrap();
+        assert_eq!(config.bars.len(), 1);
+        assert_eq!(config.bars[0].anchor, Anchor::Bottom);
+        assert_eq!(config.bars[0].height, Some(24));
+
+        let err = parse("anchor = \"Bottom\"\nheigth = 24").err().unwrap().to_string();
+        assert!(err.starts_with("unknown field `heigth`"), "{}", err);
+
+        assert!(parse("anchor = \"Bottom\"\n[[bar]]").is_err());
+        assert_eq!(parse("").unwrap().bars.len(), 1);
+    }
+
+    #[test]
+    fn bars_have_no_widgets_by_default() {
+        let config = parse("[[bar]]\nleft = [\"x\"]").unwrap();
+        assert_eq!(config.bars[0].left, vec![WidgetConfig::text("x")]);
+        assert!(config.bars[0].center.is_empty());
+
+        assert_eq!(Config::default().bars[0].center, default_center());
+        assert_eq!(parse("anchor = \"Bottom\"").unwrap().bars[0].center, default_center());
+        assert!(parse("center = []").unwrap().bars[0].center.is_empty());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(toml::from_str::<Bar>("heigth = 30").is_err());
+        assert!(toml::from_str::<Bar>("[output.\"DP-1\"]\nmargin = { top = 4 }").is_err());
+    }
+}
@@ -0,0 +1,63 @@
+//! Discovery of the configuration file.
+//!
+//! Unless a path is given explicitly, the configuration file is looked up
+//! as `owl/config.toml` relative to `$XDG_CONFIG_HOME` (defaulting to `~/.config`)
+//! and then relative to each of the directories in `$XDG_CONFIG_DIRS`
+//! (defaulting to `/etc/xdg`), in that order.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use crate::error::{Error, Kind, Result};
+
+const CONFIG_FILE: &str = "owl/config.toml";
+const DEFAULT_CONFIG_HOME: &str = "~/.config";
+const DEFAULT_CONFIG_DIRS: &str = "/etc/xdg";
+
+/// Expands a leading `~` in the given path to the home directory of the current user.
+/// The path is returned unchanged if it does not start with `~` or if `$HOME` is unset.
+pub fn expand_home<P: AsRef<Path>>(path: P) -> PathBuf {
+    let path = path.as_ref();
+    match (path.strip_prefix("~"), env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => Path::new(&home).join(rest),
+        _ => path.to_owned(),
+    }
+}
+
+/// Reads an XDG base directory variable, falling back to the given default
+/// if it is unset or empty as mandated by the specification.
+fn xdg_var(name: &str, default: &str) -> String {
+    env::var(name)
+        .ok()
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| default.to_owned())
+}
+
+/// Lists the locations at which a configuration file is searched for,
+/// ordered from highest to lowest precedence.
+pub fn search_paths() -> Vec<PathBuf> {
+    let config_home = xdg_var("XDG_CONFIG_HOME", DEFAULT_CONFIG_HOME);
+    let config_dirs = xdg_var("XDG_CONFIG_DIRS", DEFAULT_CONFIG_DIRS);
+
+    std::iter::once(config_home.as_str())
+        .chain(config_dirs.split(':'))
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| expand_home(dir).join(CONFIG_FILE))
+        .collect()
+}
+
+/// Finds the configuration file with the highest precedence.
+/// If none exist, the returned error lists every path that was tried.
+pub fn locate() -> Result<PathBuf> {
+    let candidates = search_paths();
+
+    candidates.iter()
+        .find(|path| path.is_file())
+        .cloned()
+        .ok_or_else(|| {
+            let tried = candidates.iter()
+                .map(|path| format!("\n  {}", path.display()))
+                .collect::<String>();
+            Error::new(Kind::IoError)
+                .with_msg(format!("No configuration file found. Searched:{}", tried))
+        })
+}
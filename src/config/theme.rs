@@ -0,0 +1,136 @@
+//! Configuration of the theme shared by all bars, along with the named styles
+//! that widgets may refer to.
+
+use std::collections::HashMap;
+use std::fmt::Formatter;
+use serde::{de, Deserialize, Deserializer};
+use crate::config::Color;
+
+/// The `[theme]` section of the configuration file.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// The name of a font family, or one of `sans-serif`, `serif`, `monospace` and `system-ui`.
+    pub font_family: String,
+    pub font_size: f64,
+    /// Between 1 and 1000, where 400 is regular and 700 is bold.
+    pub font_weight: u16,
+    pub foreground: Color,
+    pub background: Color,
+    /// The color of highlighted elements, such as the focused workspace.
+    pub accent: Color,
+    /// The color of elements that demand attention, such as an almost empty battery.
+    pub urgent: Color,
+    /// The color of dimmed elements, such as unfocused workspaces.
+    pub inactive: Color,
+    /// The radius of the corners of widgets that draw an outline, such as containers.
+    pub border_radius: f64,
+    /// The padding of widgets that do not configure their own.
+    pub padding: f64,
+    /// Named styles, written as `[theme.style.warning]`, that widgets refer to by name.
+    #[serde(rename = "style")]
+    pub styles: HashMap<String, StyleConfig>,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        ThemeConfig {
+            font_family: "sans-serif".to_owned(),
+            font_size: 12.0,
+            font_weight: 400,
+            foreground: Color::WHITE,
+            background: Color::BLACK,
+            accent: Color::rgb(0x52, 0x94, 0xE2),
+            urgent: Color::rgb(0xE5, 0x39, 0x35),
+            inactive: Color::rgb(0x88, 0x88, 0x88),
+            border_radius: 0.0,
+            padding: 0.0,
+            styles: HashMap::new(),
+        }
+    }
+}
+
+/// Overrides of the options of the theme. Any option that is left out is inherited,
+/// either from the named style that this one `extends` or from the theme itself.
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct StyleConfig {
+    pub extends: Option<String>,
+    pub font_family: Option<String>,
+    pub font_size: Option<f64>,
+    pub font_weight: Option<u16>,
+    pub foreground: Option<Color>,
+    pub background: Option<Color>,
+    pub accent: Option<Color>,
+    pub urgent: Option<Color>,
+    pub inactive: Option<Color>,
+    pub border_radius: Option<f64>,
+    pub padding: Option<f64>,
+}
+
+/// The style of a widget, written either as the name of a style, such as `style = "warning"`,
+/// or as overrides of its own, such as `style = { extends = "warning", foreground = "#FF0000" }`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StyleRef {
+    Named(String),
+    Inline(StyleConfig),
+}
+
+impl<'de> Deserialize<'de> for StyleRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = StyleRef;
+
+            fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                write!(f, "the name of a style or a table of style options")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> std::result::Result<StyleRef, E> {
+                Ok(StyleRef::Named(name.to_owned()))
+            }
+
+            fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> std::result::Result<StyleRef, A::Error> {
+                StyleConfig::deserialize(de::value::MapAccessDeserializer::new(map)).map(StyleRef::Inline)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize, Debug)]
+    struct Widget {
+        style: StyleRef,
+    }
+
+    fn style(src: &str) -> std::result::Result<StyleRef, String> {
+        toml::from_str::<Widget>(src).map(|widget| widget.style).map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn styles_are_named_or_inline() {
+        assert_eq!(style("style = \"warning\""), Ok(StyleRef::Named("warning".to_owned())));
+
+        let inline = StyleConfig {
+            extends: Some("warning".to_owned()),
+            foreground: Some(Color::rgb(0xFF, 0, 0)),
+            ..StyleConfig::default()
+        };
+        assert_eq!(style("style = { extends = \"warning\", foreground = \"#FF0000\" }"), Ok(StyleRef::Inline(inline)));
+    }
+
+    #[test]
+    fn unknown_style_options_are_named() {
+        let err = style("style = { foregroud = \"#FF0000\" }").unwrap_err();
+        assert!(err.starts_with("unknown field `foregroud`"), "{}", err);
+
+        let err = style("style = 4").unwrap_err();
+        assert!(err.contains("the name of a style or a table of style options"), "{}", err);
+    }
+}
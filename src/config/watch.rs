@@ -0,0 +1,68 @@
+//! Event sources that request the configuration to be reloaded,
+//! either when the configuration file changes or when SIGUSR1 is received.
+//!
+//! Neither source reloads the configuration by itself; they merely set
+//! `SharedLoopData::reload_requested`, leaving it to the main loop to act on it.
+
+use std::ffi::OsStr;
+use std::path::Path;
+use inotify::{Inotify, WatchMask};
+use log::{info, trace};
+use crate::calloop::{Interest, Mode, PostAction};
+use crate::calloop::generic::Generic;
+use crate::calloop::signals::{Signal, Signals};
+use crate::error::{Error, Kind, Result};
+use crate::LoopHandle;
+
+/// Watches the given configuration file for changes.
+///
+/// The parent directory is watched rather than the file itself, since many editors
+/// save files by replacing them, which would otherwise silently end the watch.
+/// Newly created files are only reloaded once they have been written and closed,
+/// such that an empty file is never mistaken for an empty configuration.
+pub fn watch_file(path: &Path, handle: &LoopHandle) -> Result<()> {
+    let file_name = path.file_name()
+        .map(OsStr::to_owned)
+        .ok_or_else(|| Error::new(Kind::IoError)
+            .with_msg(format!("Unable to watch `{}`, it does not name a file.", path.display())))?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut inotify = Inotify::init()?;
+    inotify.add_watch(dir, WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO)?;
+
+    let mut buffer = [0u8; 1024];
+    handle
+        .insert_source(
+            Generic::new(inotify, Interest::READ, Mode::Level),
+            move |_, inotify, data| {
+                let changed = inotify.read_events(&mut buffer)?
+                    .any(|event| event.name == Some(file_name.as_os_str()));
+
+                if changed {
+                    info!("Configuration file changed, reloading.");
+                    data.reload_requested = true;
+                }
+                Ok(PostAction::Continue)
+            },
+        )
+        .map(|_| trace!("Watching `{}` for changes.", dir.display()))
+        .map_err(|err| Error::new(Kind::IoError)
+            .with_msg(format!("Unable to insert config watch into EventLoop. Error: {}", err)))
+}
+
+/// Requests a reload whenever the process receives SIGUSR1.
+pub fn watch_signal(handle: &LoopHandle) -> Result<()> {
+    let signals = Signals::new(&[Signal::SIGUSR1])?;
+
+    handle
+        .insert_source(signals, |_, _, data| {
+            info!("SIGUSR1 received, reloading configuration.");
+            data.reload_requested = true;
+        })
+        .map(|_| ())
+        .map_err(|err| Error::new(Kind::IoError)
+            .with_msg(format!("Unable to insert signal source into EventLoop. Error: {}", err)))
+}
@@ -0,0 +1,109 @@
+//! Configuration of the widgets placed on a bar.
+
+use std::fmt::{Display, Formatter};
+use serde::{de, Deserialize, Deserializer};
+use toml::value::{Table, Value};
+use crate::config::StyleRef;
+
+/// Options of a widget that concern its placement on the bar or within a container,
+/// rather than the widget itself. Widths are measured along the bar, which makes them
+/// heights on vertical bars, or along the direction of the container.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Placement {
+    pub min_width: Option<f64>,
+    /// Widgets that would be wider are shortened, as they would be if the bar overflowed.
+    pub max_width: Option<f64>,
+    /// The space between the contents of the widget and either end of its place on the bar.
+    /// Defaults to the padding of the theme.
+    pub padding: Option<f64>,
+    /// Widgets of lower priority are shortened or hidden first when the bar overflows.
+    pub priority: i32,
+    /// The share of the free space of a container that the widget grows by.
+    pub grow: f64,
+    /// The share, relative to its width, by which the widget shrinks when a container overflows.
+    pub shrink: f64,
+}
+
+impl Placement {
+    const KEYS: [&'static str; 6] = ["min_width", "max_width", "padding", "priority", "grow", "shrink"];
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Placement {
+            min_width: None,
+            max_width: None,
+            padding: None,
+            priority: 0,
+            grow: 0.0,
+            shrink: 1.0,
+        }
+    }
+}
+
+/// A widget placed on a bar, written as a table such as
+/// `[[bar.left]] widget = "core:sway.focused_view_name"`, with the options of the widget
+/// alongside `widget`. A plain string is shorthand for a text widget with that format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WidgetConfig {
+    /// `text` for the builtin text widget, otherwise `module.widget`.
+    pub widget: String,
+    pub placement: Placement,
+    /// Given by the `style` entry of the table, which applies to the widget and its children.
+    pub style: Option<StyleRef>,
+    /// The remaining entries of the table, passed on to `Widget::configure`.
+    pub options: Table,
+}
+
+impl WidgetConfig {
+    /// A text widget displaying the given format string.
+    pub fn text<T: Into<String>>(format: T) -> Self {
+        let mut options = Table::new();
+        options.insert("format".to_owned(), Value::String(format.into()));
+        WidgetConfig { widget: "text".to_owned(), placement: Placement::default(), style: None, options }
+    }
+
+    /// The options of the widget as a value that widgets deserialize into their own options.
+    pub fn options(&self) -> Value {
+        Value::Table(self.options.clone())
+    }
+}
+
+impl Display for WidgetConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.widget)
+    }
+}
+
+impl<'de> Deserialize<'de> for WidgetConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Format(String),
+            Table(Table),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Format(format) => Ok(WidgetConfig::text(format)),
+            Raw::Table(mut options) => {
+                let widget = match options.remove("widget") {
+                    Some(Value::String(widget)) => widget,
+                    Some(_) => return Err(de::Error::custom("`widget` must be a string")),
+                    None => return Err(de::Error::missing_field("widget")),
+                };
+
+                let placement = Placement::KEYS.iter()
+                    .filter_map(|key| options.remove(*key).map(|value| (key.to_string(), value)))
+                    .collect::<Table>();
+                let placement = Value::Table(placement).try_into().map_err(de::Error::custom)?;
+                let style = options.remove("style")
+                    .map(|style| style.try_into().map_err(de::Error::custom))
+                    .transpose()?;
+
+                Ok(WidgetConfig { widget, placement, style, options })
+            }
+        }
+    }
+}
@@ -21,7 +21,7 @@ pub fn gather_err<V, E, F>(mut f: F) -> std::result::Result<V, E>
 ///
 /// Fatal errors are errors that the module is unable to recover from.
 /// When a fatal error is encountered the module will be unloaded.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Severity {
     Warning,
     Fatal,
@@ -32,6 +32,7 @@ pub enum Kind {
     IoError,
     UdevError,
     WaylandError,
+    ConfigError,
     ModuleError(Severity),
     Generic(Box<dyn std::error::Error>),
 }
@@ -55,6 +56,14 @@ impl Error {
         self
     }
 
+    /// The severity of the error. Errors that did not originate from a module are fatal.
+    pub fn severity(&self) -> Severity {
+        match self.kind {
+            Kind::ModuleError(severity) => severity,
+            _ => Severity::Fatal,
+        }
+    }
+
     pub fn udev_invalid_device_attribute(device: &str, attr: &str) -> Self {
         Self {
             kind: Kind::UdevError,
@@ -83,6 +92,21 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<toml::de::Error> for Error {
+    fn from(err: toml::de::Error) -> Self {
+        Self {
+            kind: Kind::ConfigError,
+            msg: Some(err.to_string()),
+        }
+    }
+}
+
+impl From<piet::Error> for Error {
+    fn from(err: piet::Error) -> Self {
+        Self { kind: Kind::Generic(Box::new(err)), msg: None }
+    }
+}
+
 impl From<Box<dyn std::error::Error>> for Error {
     fn from(err: Box<dyn std::error::Error>) -> Self {
         Self { kind: Kind::Generic(err), msg: None }
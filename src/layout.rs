@@ -0,0 +1,295 @@
+//! Arrangement of widgets along a bar.
+//!
+//! Widgets are placed in three groups: the left group starts at the start of the bar, the right
+//! group ends at its end and the center group is centered on the bar, as far as the other groups
+//! allow. Everything is measured along the bar, such that the arrangement applies to horizontal
+//! and vertical bars alike.
+
+use std::cmp::Reverse;
+use crate::config::Overflow;
+
+/// The group of a bar that a widget belongs to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Group {
+    Left,
+    Center,
+    Right,
+}
+
+/// A widget to be arranged.
+#[derive(Debug, Copy, Clone)]
+pub struct Item {
+    pub group: Group,
+    /// The length that the widget would like to take up.
+    pub length: f64,
+    /// The length below which the widget is hidden rather than shortened.
+    pub min_length: f64,
+    /// Widgets of lower priority are shortened or hidden first.
+    pub priority: i32,
+}
+
+/// A range along the bar.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Span {
+    pub start: f64,
+    pub length: f64,
+}
+
+impl Span {
+    pub fn end(&self) -> f64 {
+        self.start + self.length
+    }
+}
+
+pub struct Options {
+    /// The space between adjacent widgets, as well as between widgets and separators.
+    pub spacing: f64,
+    /// The space between the ends of the bar and the left and right groups.
+    pub padding: f64,
+    /// The length of the separator placed between adjacent widgets of a group, if any.
+    pub separator: Option<f64>,
+    pub overflow: Overflow,
+}
+
+impl Options {
+    /// The space between two adjacent widgets of a group.
+    fn gap(&self) -> f64 {
+        match self.separator {
+            Some(separator) => 2.0 * self.spacing + separator,
+            None => self.spacing,
+        }
+    }
+}
+
+pub struct Arrangement {
+    /// The span of every item, in order, or `None` if the item has been hidden.
+    pub items: Vec<Option<Span>>,
+    /// The spans of the separators between adjacent widgets.
+    pub separators: Vec<Span>,
+}
+
+/// Arranges the items on a bar of the given length. Items are given the length they ask for,
+/// unless the bar would overflow, in which case the items of the lowest priority, and of those
+/// the last ones, are shortened or hidden first. Items are only hidden if shortening the
+/// remaining items does not suffice, and are then shortened no more than necessary.
+pub fn arrange(items: &[Item], length: f64, options: &Options) -> Arrangement {
+    let available = (length - 2.0 * options.padding).max(0.0);
+
+    let mut order = (0..items.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| (items[i].priority, Reverse(i)));
+
+    let mut hidden = vec![false; items.len()];
+    let mut to_hide = order.iter();
+    let lengths = loop {
+        let mut lengths = items.iter()
+            .zip(&hidden)
+            .map(|(item, &hidden)| if hidden { None } else { Some(item.length) })
+            .collect::<Vec<_>>();
+
+        if options.overflow == Overflow::Ellipsize {
+            shorten(items, &mut lengths, &order, available, options);
+        }
+
+        if total_length(items, &lengths, options) <= available {
+            break lengths;
+        }
+        match to_hide.next() {
+            Some(&i) => hidden[i] = true,
+            None => break lengths,
+        }
+    };
+
+    let group_length = |group| group_length(items, &lengths, group, options);
+    let spacing_after = |group| if group_length(group) > 0.0 { options.spacing } else { 0.0 };
+
+    let left = options.padding;
+    let right = length - options.padding - group_length(Group::Right);
+    let center_length = group_length(Group::Center);
+    let center = ((length - center_length) / 2.0)
+        .min(right - spacing_after(Group::Right) - center_length)
+        .max(left + group_length(Group::Left) + spacing_after(Group::Left));
+
+    let mut arrangement = Arrangement {
+        items: vec![None; items.len()],
+        separators: Vec::new(),
+    };
+    for (group, start) in [(Group::Left, left), (Group::Center, center), (Group::Right, right)] {
+        place_group(items, &lengths, group, start, options, &mut arrangement);
+    }
+    arrangement
+}
+
+/// Shortens the items in the given order until they fit within `available`, down to their
+/// minimum length. Items that would have to be shortened to nothing are hidden.
+fn shorten(items: &[Item], lengths: &mut [Option<f64>], order: &[usize], available: f64, options: &Options) {
+    for &i in order {
+        let excess = total_length(items, lengths, options) - available;
+        if excess <= 0.0 {
+            break;
+        }
+        if let Some(length) = lengths[i] {
+            let shortened = (length - excess).max(items[i].min_length);
+            lengths[i] = if shortened > 0.0 { Some(shortened.min(length)) } else { None };
+        }
+    }
+}
+
+/// The length of a group, given the lengths of the items that have not been hidden.
+fn group_length(items: &[Item], lengths: &[Option<f64>], group: Group, options: &Options) -> f64 {
+    let visible = items.iter()
+        .zip(lengths)
+        .filter(|(item, _)| item.group == group)
+        .filter_map(|(_, length)| *length)
+        .collect::<Vec<_>>();
+
+    visible.iter().sum::<f64>() + options.gap() * visible.len().saturating_sub(1) as f64
+}
+
+/// The length of all groups, including the spacing between them.
+fn total_length(items: &[Item], lengths: &[Option<f64>], options: &Options) -> f64 {
+    let groups = [Group::Left, Group::Center, Group::Right]
+        .iter()
+        .map(|&group| group_length(items, lengths, group, options))
+        .filter(|&length| length > 0.0)
+        .collect::<Vec<_>>();
+
+    groups.iter().sum::<f64>() + options.spacing * groups.len().saturating_sub(1) as f64
+}
+
+/// Places the visible items of a group one after another, starting at `start`.
+fn place_group(
+    items: &[Item],
+    lengths: &[Option<f64>],
+    group: Group,
+    start: f64,
+    options: &Options,
+    arrangement: &mut Arrangement,
+) {
+    let mut offset = start;
+    let mut first = true;
+
+    for (i, item) in items.iter().enumerate() {
+        let length = match lengths[i] {
+            Some(length) if item.group == group => length,
+            _ => continue,
+        };
+
+        if !first {
+            match options.separator {
+                Some(separator) => {
+                    arrangement.separators.push(Span { start: offset + options.spacing, length: separator });
+                    offset += options.gap();
+                }
+                None => offset += options.spacing,
+            }
+        }
+        first = false;
+
+        arrangement.items[i] = Some(Span { start: offset, length });
+        offset += length;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(group: Group, length: f64) -> Item {
+        Item { group, length, min_length: 0.0, priority: 0 }
+    }
+
+    fn options(padding: f64, separator: Option<f64>, overflow: Overflow) -> Options {
+        Options { spacing: 10.0, padding, separator, overflow }
+    }
+
+    fn span(start: f64, length: f64) -> Option<Span> {
+        Some(Span { start, length })
+    }
+
+    #[test]
+    fn groups_are_placed_at_the_start_center_and_end() {
+        let items = [
+            item(Group::Left, 20.0),
+            item(Group::Left, 30.0),
+            item(Group::Center, 40.0),
+            item(Group::Right, 10.0),
+        ];
+        let arrangement = arrange(&items, 200.0, &options(5.0, None, Overflow::Ellipsize));
+
+        assert_eq!(arrangement.items, [span(5.0, 20.0), span(35.0, 30.0), span(80.0, 40.0), span(185.0, 10.0)]);
+        assert!(arrangement.separators.is_empty());
+    }
+
+    #[test]
+    fn separators_are_placed_between_widgets_of_a_group() {
+        let items = [item(Group::Left, 20.0), item(Group::Left, 30.0), item(Group::Right, 10.0)];
+        let arrangement = arrange(&items, 200.0, &options(5.0, Some(4.0), Overflow::Ellipsize));
+
+        assert_eq!(arrangement.items, [span(5.0, 20.0), span(49.0, 30.0), span(185.0, 10.0)]);
+        assert_eq!(arrangement.separators, [Span { start: 35.0, length: 4.0 }]);
+    }
+
+    #[test]
+    fn center_group_is_kept_clear_of_the_others() {
+        let options = options(5.0, None, Overflow::Ellipsize);
+
+        let items = [item(Group::Left, 100.0), item(Group::Center, 40.0)];
+        assert_eq!(arrange(&items, 200.0, &options).items, [span(5.0, 100.0), span(115.0, 40.0)]);
+
+        let items = [item(Group::Center, 40.0), item(Group::Right, 100.0)];
+        assert_eq!(arrange(&items, 200.0, &options).items, [span(45.0, 40.0), span(95.0, 100.0)]);
+    }
+
+    #[test]
+    fn lowest_priority_is_shortened_first() {
+        let items = [
+            Item { min_length: 10.0, priority: 1, ..item(Group::Left, 60.0) },
+            Item { min_length: 20.0, ..item(Group::Right, 60.0) },
+        ];
+        let arrangement = arrange(&items, 100.0, &options(0.0, None, Overflow::Ellipsize));
+
+        assert_eq!(arrangement.items, [span(0.0, 60.0), span(70.0, 30.0)]);
+    }
+
+    #[test]
+    fn widgets_are_shortened_no_further_than_their_minimum() {
+        let items = [
+            Item { min_length: 10.0, priority: 1, ..item(Group::Left, 60.0) },
+            Item { min_length: 50.0, ..item(Group::Right, 60.0) },
+        ];
+        let arrangement = arrange(&items, 100.0, &options(0.0, None, Overflow::Ellipsize));
+
+        assert_eq!(arrangement.items, [span(0.0, 40.0), span(50.0, 50.0)]);
+    }
+
+    #[test]
+    fn widgets_are_hidden_when_shortening_does_not_suffice() {
+        let items = [
+            Item { min_length: 55.0, priority: 1, ..item(Group::Left, 60.0) },
+            Item { min_length: 55.0, ..item(Group::Right, 60.0) },
+        ];
+        let arrangement = arrange(&items, 100.0, &options(0.0, None, Overflow::Ellipsize));
+
+        assert_eq!(arrangement.items, [span(0.0, 60.0), None]);
+    }
+
+    #[test]
+    fn hide_overflow_hides_without_shortening() {
+        let items = [
+            Item { priority: 1, ..item(Group::Left, 60.0) },
+            item(Group::Right, 60.0),
+        ];
+        let arrangement = arrange(&items, 100.0, &options(0.0, None, Overflow::Hide));
+
+        assert_eq!(arrangement.items, [span(0.0, 60.0), None]);
+    }
+
+    #[test]
+    fn later_widgets_of_equal_priority_are_hidden_first() {
+        let items = [item(Group::Left, 60.0), item(Group::Left, 20.0), item(Group::Left, 60.0)];
+        let arrangement = arrange(&items, 110.0, &options(0.0, Some(2.0), Overflow::Hide));
+
+        assert_eq!(arrangement.items, [span(0.0, 60.0), span(82.0, 20.0), None]);
+        assert_eq!(arrangement.separators, [Span { start: 70.0, length: 2.0 }]);
+    }
+}
@@ -1,36 +1,81 @@
-use std::{sync::RwLock, rc::Rc, collections::HashMap};
+use std::{sync::RwLock, rc::Rc, collections::HashSet};
 use smithay_client_toolkit::reexports::calloop;
-use modules::Module;
+use modules::{Module, ModuleRegistry};
 
+pub mod config;
 pub mod error;
+pub mod layout;
 pub mod modules;
+mod protocols;
+pub mod theme;
 pub mod wayland;
+pub mod widgets;
 
 pub use modules::prelude as module_prelude;
 
-// TODO check whether `Box<dyn Module>` can be substituted with `dyn Module`
-pub type Modules = Rc<RwLock<HashMap<&'static str, Box<dyn Module>>>>;
+pub type Modules = Rc<RwLock<ModuleRegistry>>;
 
-pub struct UpdateHandle(bool);
+/// Describes which parts of the bar are in need of being redrawn.
+#[derive(Clone, Debug)]
+pub enum Dirty {
+    /// The entire bar must be redrawn.
+    All,
+    /// Only the widgets belonging to the modules with the given names must be redrawn.
+    Modules(HashSet<&'static str>),
+}
+
+impl Dirty {
+    /// Combines two dirty states into one that covers both.
+    pub fn merge(self, other: Dirty) -> Dirty {
+        match (self, other) {
+            (Dirty::Modules(mut a), Dirty::Modules(b)) => {
+                a.extend(b);
+                Dirty::Modules(a)
+            }
+            _ => Dirty::All,
+        }
+    }
+}
+
+/// Tracks which modules have changed state since the bar was last drawn.
+pub struct UpdateHandle(Option<Dirty>);
 
 impl UpdateHandle {
     pub fn new() -> Self {
-        UpdateHandle(true)
+        UpdateHandle(Some(Dirty::All))
+    }
+
+    /// Marks the module with the given name (see `Named::NAME`) as changed.
+    pub fn update(&mut self, module: &'static str) {
+        let mut changed = HashSet::new();
+        changed.insert(module);
+        self.mark(Dirty::Modules(changed));
+    }
+
+    fn mark(&mut self, dirty: Dirty) {
+        self.0 = Some(match self.0.take() {
+            Some(current) => current.merge(dirty),
+            None => dirty,
+        });
     }
 
-    pub fn update(&mut self) {
-        self.0 = true;
+    /// Returns the changes signalled since the last call,
+    /// resetting the handle in the process.
+    pub fn take(&mut self) -> Option<Dirty> {
+        self.0.take()
     }
 }
 
 pub struct SharedLoopData {
     pub update_handle: UpdateHandle,
     pub modules: Modules,
+    /// Set when the configuration should be reloaded, see `config::watch_file`.
+    pub reload_requested: bool,
 }
 
 pub type EventLoop<'l> = calloop::EventLoop<'l, SharedLoopData>;
 pub type LoopHandle<'l> = calloop::LoopHandle<'l, SharedLoopData>;
 
 impl SharedLoopData {
-    pub fn update(&mut self) { self.update_handle.update(); }
+    pub fn update(&mut self, module: &'static str) { self.update_handle.update(module); }
 }
\ No newline at end of file
@@ -1,220 +1,249 @@
-use smithay_client_toolkit::{
-    default_environment,
-    environment::SimpleGlobal,
-    new_default_environment,
-    output::{with_output_info, OutputInfo},
-    reexports::{
-        calloop,
-        client::protocol::{wl_output, wl_shm, wl_surface},
-        client::{Attached, Main},
-        protocols::wlr::unstable::layer_shell::v1::client as wlr_client,
-        protocols::wlr::unstable::layer_shell::v1::client::{
-            zwlr_layer_shell_v1, zwlr_layer_surface_v1,
-        },
-    },
-    shm::AutoMemPool,
-    WaylandSource,
-};
-use owl::{SharedLoopData, UpdateHandle, EventLoop};
+use owl::{SharedLoopData, UpdateHandle, EventLoop, LoopHandle};
 
 use argh::FromArgs;
-use std::cell::{Cell, RefCell};
-use std::collections::HashMap;
-use std::io::Read;
-use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::RwLock;
-use log::error;
-use piet::{ImageFormat, RenderContext, TextLayoutBuilder};
-use piet::{kurbo::Rect, Color};
-use piet::kurbo::Point;
-use piet_common::{BitmapTarget, CairoRenderContext, CairoTextLayout, CairoTextLayoutBuilder, Device, Text};
+use log::{error, info, warn};
+use owl::config;
+use owl::modules::ModuleRegistry;
 use owl::wayland::{SurfaceAction, WaylandContext};
 
-mod config;
-mod udev;
 //mod bar;
 
-const DEFAULT_CONFIG_PATH: &str = "~/.config/.rs-bar";
-
 #[derive(FromArgs)]
 #[argh(description = "Wayland status bar")]
 struct Args {
     /// path to configuration file
     #[argh(option, short = 'c')]
     config: Option<String>,
-}
-
-impl Args {
-    fn load_config(&self) -> Result<config::Bar, impl std::error::Error> {
-        let path = self.config.as_ref()
-            .map(|s| s.as_str())
-            .unwrap_or(DEFAULT_CONFIG_PATH);
-
-        let src = std::fs::File::open(path)
-            .and_then(|mut f| {
-                let mut s = String::new();
-                f.read_to_string(&mut s)
-                    .map(|_| s)
-            })?;
-
-        toml::from_str(src.as_str())
-            .map_err(|err| <toml::de::Error as Into<std::io::Error>>::into(err))
-    }
-}
 
-/*impl Surface {
-    fn new(
-        output: &wl_output::WlOutput,
-        surface: wl_surface::WlSurface,
-        layer_shell: &Attached<zwlr_layer_shell_v1::ZwlrLayerShellV1>,
-        pool: AutoMemPool,
-        modules: owl::Modules, // TODO merge modules and config to one
-        config: Rc<config::Bar>,
-    ) -> Self {
-
-
-        layer_surface.set_size(1920, 30);
-        // Anchor to the top left corner of the output
-        layer_surface.set_anchor(config::Anchor::Top.into());
-        layer_surface.set_exclusive_zone(30);
-
-        let next_render_event = Rc::new(Cell::new(None::<RenderEvent>));
-        let next_render_event_handle = Rc::clone(&next_render_event);
-        layer_surface.quick_assign(move |layer_surface, event, _| {
-            match (event, next_render_event_handle.get()) {
-                (zwlr_layer_surface_v1::Event::Closed, _) => {
-                    next_render_event_handle.set(Some(RenderEvent::Closed));
-                }
-                (zwlr_layer_surface_v1::Event::Configure { serial, width, height }, next)
-                if next != Some(RenderEvent::Closed) =>
-                    {
-                        layer_surface.ack_configure(serial);
-                        next_render_event_handle.set(Some(RenderEvent::Configure { width, height }));
-                    }
-                (_, _) => {}
-            }
-        });
+    /// validate the configuration file and exit
+    #[argh(switch)]
+    check_config: bool,
 
-        // Commit so that the server will send a configure event
-        surface.commit();
+    /// list the available modules and their fields, then exit
+    #[argh(switch)]
+    list_modules: bool,
 
-        Self { surface, layer_surface, next_render_event, pool, dimensions: (0, 0), modules, config }
-    }
+    /// list the available widgets, then exit
+    #[argh(switch)]
+    list_widgets: bool,
+}
 
-    /// Handles any events that have occurred since the last call, redrawing if needed.
-    /// Returns true if the surface should be dropped.
-    fn handle_events(&mut self) -> bool {
-        match self.next_render_event.take() {
-            Some(RenderEvent::Closed) => true,
-            Some(RenderEvent::Configure { width, height }) => {
-                if self.dimensions != (width, height) {
-                    self.dimensions = (width, height);
-                    self.draw();
-                }
-                false
-            }
-            None => false,
+impl Args {
+    /// Resolves the path of the configuration file, either from the command line
+    /// or by searching the default locations. Returns `None` if no configuration
+    /// file could be found in any of the default locations.
+    fn config_path(&self) -> Option<PathBuf> {
+        match &self.config {
+            Some(path) => Some(config::expand_home(path)),
+            None => config::locate()
+                .map_err(|err| warn!("{}", err))
+                .ok(),
         }
     }
 
-    fn draw(&mut self) {
-        let stride = 4 * self.dimensions.0 as i32;
-        let width = self.dimensions.0 as i32;
-        let height = self.dimensions.1 as i32;
+}
 
-        // Note: unwrap() is only used here in the interest of simplicity of the example.
-        // A "real" application should handle the case where both pools are still in use by the
-        // compositor.
-        let (canvas, buffer) =
-            self.pool.buffer(width, height, stride, wl_shm::Format::Argb8888).unwrap();
+/// Loads the configuration, falling back to the default configuration
+/// if no configuration file exists. A configuration file that exists,
+/// but cannot be parsed, results in an error.
+fn load_config(path: Option<&Path>) -> owl::error::Result<config::Config> {
+    let path = match path {
+        Some(path) => path,
+        None => {
+            info!("Using the default configuration.");
+            return Ok(config::Config::default());
+        }
+    };
 
+    config::load(path)
+        .map_err(|err| {
+            error!("Unable to load config: {}", path.display());
+            err
+        })
+}
 
-        let mut surface = PietWaylandSurface::new(canvas, width, height, stride);
-        let mut rc = surface.get_context();
+/// Validates the configuration file at the given path, printing any problems found.
+/// Returns whether the configuration is valid.
+fn check_config(path: Option<&Path>) -> bool {
+    let path = match path {
+        Some(path) => path,
+        None => {
+            eprintln!("No configuration file found to check.");
+            return false;
+        }
+    };
 
-        rc.fill(
-            Rect::new(0.0, 0.0, 1920.0, 30.0),
-            &Color::rgba8(0, 255, 0, 255));
+    let src = match std::fs::read_to_string(path) {
+        Ok(src) => src,
+        Err(err) => {
+            eprintln!("{}: {}", path.display(), err);
+            return false;
+        }
+    };
 
+    let registry = match build_registry() {
+        Some(registry) => registry,
+        None => return false,
+    };
 
-        let mut text_source = String::new();
-        if let Ok(modules) = self.modules.read() {
-            let _ = modules.get("battery")
-                .map(|m| m.write("charge", &mut text_source));
+    let problems = config::check(&src, &registry);
+    for problem in &problems {
+        match problem.position {
+            Some(_) => eprintln!("{}:{}", path.display(), problem),
+            None => eprintln!("{}: {}", path.display(), problem),
         }
+    }
 
-        let mut text = piet_common::CairoText::new();
-        let mut layout_builder = text.new_text_layout(text_source)
-            .text_color(Color::rgba8(0xFF, 0, 0, 0xFF))
-            .alignment(piet_common::TextAlignment::Center)
-            .build()
-            .expect("Unable build text layout");
-
-        rc.draw_text(&layout_builder, Point::new(600.0, 10.0));
+    if problems.is_empty() {
+        println!("{}: ok", path.display());
+    }
+    problems.is_empty()
+}
 
-        rc.finish().unwrap();
+/// Builds the module registry for the commands that inspect it,
+/// printing the error if it could not be built.
+fn build_registry() -> Option<ModuleRegistry> {
+    owl::modules::build_module_registry()
+        .map_err(|err| eprintln!("Unable to build module registry: {}", err))
+        .ok()
+}
 
+/// Prints every module along with its fields and widgets,
+/// marking the modules that could not be initialised on this host.
+fn list_modules(registry: &ModuleRegistry) {
+    for (i, (name, fields, loaded)) in registry.known_modules().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        if loaded {
+            println!("{}", name);
+        } else {
+            println!("{} (unavailable on this host)", name);
+        }
 
-        // Attach the buffer to the surface and mark the entire surface as damaged
-        self.surface.attach(Some(&buffer), 0, 0);
-        self.surface.damage_buffer(0, 0, width as i32, height as i32);
+        for field in fields {
+            println!("    {}.{} ({}): {}", name, field.name, field.kind, field.description);
+        }
 
-        // Finally, commit the surface
-        self.surface.commit();
+        for (_, widget) in registry.widgets().filter(|(module, _)| *module == name) {
+            println!("    widget {}.{}", name, widget);
+        }
     }
-}*/
-
-
+}
 
+/// Prints the builtin widgets followed by those registered by modules.
+fn list_widgets(registry: &ModuleRegistry) {
+    for widget in owl::widgets::BUILTIN {
+        println!("{} (builtin)", widget);
+    }
+    for (module, widget) in registry.widgets() {
+        println!("{}.{}", module, widget);
+    }
+}
 
+/// Watches the configuration file at the given path for changes, unless it is watched already.
+fn watch_config(path: &Path, watched_paths: &mut Vec<PathBuf>, handle: &LoopHandle) {
+    if watched_paths.iter().any(|watched| watched == path) {
+        return;
+    }
+    match config::watch_file(path, handle) {
+        Ok(()) => watched_paths.push(path.to_owned()),
+        Err(err) => warn!("{}", err),
+    }
+}
 
 // TODO remove/reduce unwrap usage in main
 fn main() {
+    // Show warnings unless RUST_LOG says otherwise, as they explain missing widgets and modules
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
     let args: Args = argh::from_env();
-    let config = args.load_config();
+    let mut config_path = args.config_path();
+
+    if args.check_config {
+        std::process::exit(if check_config(config_path.as_deref()) { 0 } else { 1 });
+    }
+
+    if args.list_modules || args.list_widgets {
+        let registry = match build_registry() {
+            Some(registry) => registry,
+            None => std::process::exit(1),
+        };
+        if args.list_modules {
+            list_modules(&registry);
+        }
+        if args.list_widgets {
+            if args.list_modules {
+                println!();
+            }
+            list_widgets(&registry);
+        }
+        return;
+    }
+    let config = load_config(config_path.as_deref());
 
     let config = Rc::new(match config {
-        Ok(bar) => bar,
+        Ok(config) => config,
         Err(err) => {
-            error!("{:?}", err);
-            let path = args.config.as_ref().map(String::as_str).unwrap_or(DEFAULT_CONFIG_PATH);
-            error!("Unable to load config: {}", path);
+            error!("{}", err);
             return;
         }
     });
 
-    let modules: owl::Modules = Rc::new(RwLock::new(HashMap::new()));
+    let mut event_loop = EventLoop::try_new().unwrap();
+
+    // Hooks are registered before any surface is created, since modules failing to register
+    // theirs are unloaded, which changes the widgets that surfaces are able to create
+    let mut registry = owl::modules::build_module_registry().expect("Unable to build module registry");
+    registry.register_hooks(&event_loop.handle());
+    let modules: owl::Modules = Rc::new(RwLock::new(registry));
     let modules_ref = modules.clone();
-    {
-        let mut modules = modules.write().unwrap();
-        let mut bat_mod = owl::modules::battery::BatteryModule::init()
-            .expect("Unable to initialise battery module");
-        modules.insert("battery", Box::new(bat_mod));
-    }
 
-    let wayland_context = WaylandContext::new().unwrap();
-    let mut event_loop = EventLoop::try_new().unwrap();
+    let wayland_context = WaylandContext::new(config.clone(), modules.clone()).unwrap();
     let wayland_context = wayland_context.insert_queue_in(event_loop.handle()).unwrap();
 
+    let mut watched_paths = Vec::new();
+    if let Some(path) = &config_path {
+        watch_config(path, &mut watched_paths, &event_loop.handle());
+    }
+    if let Err(err) = config::watch_signal(&event_loop.handle()) {
+        warn!("{}", err);
+    }
+
     let mut loop_data = SharedLoopData {
         update_handle: UpdateHandle::new(),
         modules: modules_ref,
+        reload_requested: false,
     };
 
     loop {
-        // This is ugly, let's hope that some version of drain_filter() gets stabilized soon
-        // https://github.com/rust-lang/rust/issues/43244
+        if std::mem::take(&mut loop_data.reload_requested) {
+            // Without an explicit path, a configuration file may have been created since
+            if args.config.is_none() {
+                config_path = args.config_path();
+                if let Some(path) = &config_path {
+                    watch_config(path, &mut watched_paths, &event_loop.handle());
+                }
+            }
+
+            match load_config(config_path.as_deref()) {
+                Ok(config) => wayland_context.reconfigure(Rc::new(config)),
+                Err(err) => error!("{}. Keeping the current configuration.", err),
+            }
+        }
+
         {
             let mut surfaces = wayland_context.surfaces.borrow_mut();
-            let mut i = 0;
-            while i != surfaces.len() {
-                if let SurfaceAction::Drop = surfaces[i].1.handle_events() {
-                    surfaces.remove(i);
-                } else {
-                    i += 1;
+            if let Some(dirty) = loop_data.update_handle.take() {
+                for surface in surfaces.values_mut() {
+                    surface.request_redraw(dirty.clone());
                 }
             }
+
+            surfaces.retain(|_, surface| !matches!(surface.handle_events(), SurfaceAction::Drop));
         }
 
         wayland_context.flush_display().unwrap();
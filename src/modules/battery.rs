@@ -1,9 +1,16 @@
-use std::collections::HashMap;
+use std::cell::RefCell;
 use std::ffi::OsStr;
 use std::fmt::{Display, Formatter, Write};
 use std::path::Path;
-use udev::Device;
-use crate::modules::{Module, Result, Error, Named};
+use std::rc::Rc;
+use log::warn;
+use crate::calloop::{Interest, Mode, PostAction, RegistrationToken};
+use crate::calloop::generic::Generic;
+use crate::LoopHandle;
+use crate::modules::{FieldInfo, Module, Result, Error, Kind, Named, Severity, Value, ValueKind};
+
+// TODO optionally grab device path from config
+const PATH: &str = "/sys/class/power_supply/BAT0";
 
 enum ChargeState {
     Charging,
@@ -27,30 +34,67 @@ impl Default for ChargeState {
     }
 }
 
-pub struct BatteryModule {
-    device: Device,
+impl ChargeState {
+    /// Interprets the `status` attribute of a power supply.
+    fn from_status(status: &str) -> Option<Self> {
+        match status {
+            "Charging" => Some(Self::Charging),
+            "Discharging" | "Not charging" => Some(Self::Discharging),
+            "Full" => Some(Self::Full),
+            _ => None,
+        }
+    }
+}
+
+/// The values of the battery as last read from sysfs.
+struct BatteryState {
     charge: u32,
     charge_state: ChargeState,
 }
 
-impl BatteryModule {
-    pub fn init() -> Result<Self> {
-        const PATH: &str = "/sys/class/power_supply/BAT0";
-        // TODO optionally grab device path from config
-        let device = udev::Device::from_syspath(&Path::new(PATH))?;
+impl BatteryState {
+    fn read() -> Result<Self> {
+        // A new device is opened on every read, since libudev caches attribute values
+        let device = udev::Device::from_syspath(Path::new(PATH))?;
 
         let charge = device.attribute_value("capacity")
             .and_then(OsStr::to_str)
             .and_then(|s| s.parse::<u32>().ok())
             .ok_or(Error::udev_invalid_device_attribute(PATH, "capacity"))?;
 
-        //dev.attribute_value("status");
-        let charge_state = ChargeState::Charging;
+        let charge_state = device.attribute_value("status")
+            .and_then(OsStr::to_str)
+            .and_then(|status| ChargeState::from_status(status.trim()))
+            .unwrap_or_default();
+
+        Ok(BatteryState { charge, charge_state })
+    }
+}
 
+pub struct BatteryModule {
+    /// Shared with the udev monitor, which refreshes it whenever the power supply changes.
+    state: Rc<RefCell<BatteryState>>,
+    reg_token: Option<RegistrationToken>,
+}
+
+impl BatteryModule {
+    pub const FIELDS: &'static [FieldInfo] = &[
+        FieldInfo {
+            name: "charge",
+            kind: ValueKind::Percentage,
+            description: "The remaining charge of the battery",
+        },
+        FieldInfo {
+            name: "charge_state",
+            kind: ValueKind::String,
+            description: "Whether the battery is Charging, Discharging or Full",
+        },
+    ];
+
+    pub fn init() -> Result<Self> {
         Ok(BatteryModule {
-            device,
-            charge,
-            charge_state,
+            state: Rc::new(RefCell::new(BatteryState::read()?)),
+            reg_token: None,
         })
     }
 }
@@ -60,12 +104,64 @@ impl Named for BatteryModule {
 }
 
 impl Module for BatteryModule {
+    fn fields(&self) -> &'static [FieldInfo] {
+        Self::FIELDS
+    }
+
     fn write(&self, field: &str, dst: &mut String) -> Result<bool> {
+        let state = self.state.borrow();
         match field {
-            "charge" => { write!(dst, "{}", self.charge).expect("Write failed");},
-            "charge_state" => {write!(dst, "{}", self.charge_state).expect("Write failed");},
-            _ => eprintln!("Unrecognized requested field {}:{}, leaving string empty.", Self::NAME, field)
+            "charge" => { write!(dst, "{}", state.charge).expect("Write failed");},
+            "charge_state" => {write!(dst, "{}", state.charge_state).expect("Write failed");},
+            _ => return Ok(false),
         }
         Ok(true)
     }
+
+    fn read(&self, field: &str) -> Result<Option<Value>> {
+        let state = self.state.borrow();
+        Ok(match field {
+            "charge" => Some(Value::Percentage(state.charge as f64)),
+            "charge_state" => Some(Value::String(state.charge_state.to_string())),
+            _ => None,
+        })
+    }
+
+    /// Monitors the power supplies through udev, re-reading the battery whenever one changes.
+    /// Failing to do so is a warning, as the values read at startup remain available.
+    fn register_hooks(&mut self, handle: LoopHandle) -> Result<()> {
+        let warning = |msg: String| Error::new(Kind::ModuleError(Severity::Warning)).with_msg(msg);
+
+        let socket = udev::MonitorBuilder::new()
+            .and_then(|builder| builder.match_subsystem("power_supply"))
+            .and_then(|builder| builder.listen())
+            .map_err(|err| warning(format!("Unable to monitor the battery for changes: {}", err)))?;
+
+        let state = Rc::clone(&self.state);
+        let token = handle
+            .insert_source(Generic::new(socket, Interest::READ, Mode::Level), move |_, socket, data| {
+                if socket.by_ref().count() == 0 {
+                    return Ok(PostAction::Continue);
+                }
+                match BatteryState::read() {
+                    Ok(read) => {
+                        *state.borrow_mut() = read;
+                        data.update(BatteryModule::NAME);
+                    }
+                    Err(err) => warn!("Unable to read the battery: {}", err),
+                }
+                Ok(PostAction::Continue)
+            })
+            .map_err(|err| warning(format!("Unable to insert udev monitor into EventLoop. Error: {}", err)))?;
+
+        self.reg_token = Some(token);
+        Ok(())
+    }
+
+    fn unregister_hooks(&mut self, handle: LoopHandle) -> Result<()> {
+        if let Some(token) = self.reg_token.take() {
+            handle.remove(token);
+        }
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -8,20 +8,30 @@
 //! The write function is a means of exposing textual information to widgets
 //! and or other modules. One such example is the text widget, which allows
 //! users to display and interpolate these exposed values as text in their bar.
+//! The same information may be read as a typed `Value` through the read function,
+//! for widgets that draw or compare values rather than display them as text.
 
 use std::collections::HashMap;
 use std::fmt::{Debug, Display, format, Formatter, write};
 use std::io;
 use std::path::Path;
 use cairo::glib::Source;
+use log::{error, warn};
+use piet::kurbo::{Rect, Size};
+use piet_common::CairoRenderContext;
 use smithay_client_toolkit::reexports::calloop::EventSource;
 use crate::calloop::{PostAction, RegistrationToken};
 use crate::{LoopHandle, SharedLoopData};
+use crate::modules::battery::BatteryModule;
 use crate::modules::sway::SwayModule;
 use crate::error::{Error, Kind, Severity, Result};
+use crate::theme::Theme;
 
 pub mod battery;
 pub mod sway;
+mod value;
+
+pub use value::{Value, ValueKind};
 
 
 
@@ -66,24 +76,48 @@ pub mod prelude {
     pub use crate::error::{
         Result, Error, Kind, Severity,
     };
+    pub use super::{FieldInfo, Value, ValueKind, Widget, WidgetContext};
+    pub use crate::theme::Theme;
+    pub use piet::kurbo::{Rect, Size};
+    pub use piet_common::CairoRenderContext;
+}
+
+/// The state that a widget is measured and drawn against.
+pub struct WidgetContext<'a> {
+    /// The module that registered the widget, whose fields the widget may read.
+    /// `None` for builtin widgets.
+    pub module: Option<&'a dyn Module>,
+    /// Every loaded module, for widgets that display the fields of several modules.
+    pub registry: &'a ModuleRegistry,
+    pub theme: &'a Theme,
 }
 
 /// Describes a widget component of a module.
 ///
 pub trait Widget {
 
-    /// Configures the widget based on user supplied configuration data.
-    /// If a fatal error is encountered during configuration of the module
-    /// the module will be dropped.
-    fn configure(&mut self) -> Result<()>;
+    /// Configures the widget based on user supplied configuration data, being the
+    /// table of the widget in the configuration file. Widgets deserialize the options
+    /// into their own structure, such as through `toml::Value::try_into`.
+    /// If a fatal error is encountered during configuration of the widget
+    /// the widget will be dropped, whereas warnings are merely logged.
+    fn configure(&mut self, options: toml::Value, registry: &ModuleRegistry) -> Result<()>;
+
+    /// Whether the widget has to be redrawn when the given module updates.
+    /// Widgets are always redrawn along with the module that registered them.
+    fn depends_on(&self, _module: &str) -> bool { false }
 
     /// Computes the space that the widget will take up. This is called
     /// whenever the widget is to be updated and re-rendered. A consecutive call
     /// to the `Widget::draw` function must never draw outside the bounds given
     /// by this functions.
-    fn compute_dimensions(&self) -> (u32, u32);
+    fn compute_dimensions(&self, rc: &mut CairoRenderContext, ctx: &WidgetContext) -> Result<Size>;
 
-    fn draw(&self);
+    /// Draws the widget within `rect`, which is smaller than the computed dimensions if the bar
+    /// has run out of space, in which case widgets should shorten their contents to fit.
+    /// Drawing is clipped to `rect`. On bars with rotated text, widgets draw unrotated
+    /// and the bar turns the result.
+    fn draw(&self, rc: &mut CairoRenderContext, rect: Rect, ctx: &WidgetContext) -> Result<()>;
 
 }
 
@@ -93,9 +127,30 @@ pub trait Named {
     const NAME: &'static str;
 }
 
+/// Describes a field exposed by a module.
+#[derive(Debug, Copy, Clone)]
+pub struct FieldInfo {
+    pub name: &'static str,
+    /// The type of the values returned by `Module::read` for the field.
+    pub kind: ValueKind,
+    pub description: &'static str,
+}
+
 pub trait Module {
+    /// The fields exposed through `Module::write` and `Module::read`.
+    fn fields(&self) -> &'static [FieldInfo];
+
+    /// Appends the textual value of the given field to `dst`.
+    /// Returns `false` if the module has no such field.
     fn write(&self, field: &str, dst: &mut String) -> Result<bool>;
 
+    /// Reads the typed value of the given field, or `None` if the module has no such field.
+    /// Unless overridden, this wraps the text written by `Module::write` in a `Value::String`.
+    fn read(&self, field: &str) -> Result<Option<Value>> {
+        let mut dst = String::new();
+        Ok(if self.write(field, &mut dst)? { Some(Value::String(dst)) } else { None })
+    }
+
     fn register_hooks(&mut self, _handle: LoopHandle) -> Result<()> { Ok(()) }
 
     fn unregister_hooks(&mut self, _handle: LoopHandle) -> Result<()> { Ok(()) }
@@ -145,13 +200,22 @@ impl<'l> WidgetRegister<'l> {
 /// Structure for managing all loaded modules and their associated widgets.1
 pub struct ModuleRegistry {
     modules: HashMap<&'static str, ModuleInfo>,
+    /// The fields of builtin modules that could not be initialised on this host.
+    unavailable: HashMap<&'static str, &'static [FieldInfo]>,
     widgets: HashMap<(&'static str, &'static str), WidgetBuilder>,
 }
 
 impl ModuleRegistry {
     /// Instantiates a new, empty, module registry.
     pub fn new() -> Self {
-        Self { modules: HashMap::new(), widgets: HashMap::new() }
+        Self { modules: HashMap::new(), unavailable: HashMap::new(), widgets: HashMap::new() }
+    }
+
+    /// Records a module that could not be initialised, such as one lacking its hardware,
+    /// such that configurations referring to its fields remain valid. Its fields are
+    /// displayed as empty, and it registers no widgets.
+    pub fn register_unavailable<M: Named>(&mut self, fields: &'static [FieldInfo]) {
+        self.unavailable.insert(M::NAME, fields);
     }
 
     pub fn register<M: Module + Named + 'static>(&mut self, mut module: M) -> Result<()> {
@@ -163,17 +227,91 @@ impl ModuleRegistry {
         Ok(())
     }
 
+    /// Registers the event sources of every module with the event loop, through which modules
+    /// signal changes to their fields. Modules that fail to do so with a fatal error are unloaded,
+    /// leaving their fields known as those of an unavailable module.
+    pub fn register_hooks(&mut self, handle: &LoopHandle) {
+        let mut failed = Vec::new();
+        for (name, info) in self.modules.iter_mut() {
+            if let Err(err) = info.module.register_hooks(handle.clone()) {
+                match err.severity() {
+                    Severity::Warning => warn!("Module `{}`: {}", name, err),
+                    Severity::Fatal => {
+                        error!("Unloading module `{}`: {}", name, err);
+                        failed.push(*name);
+                    }
+                }
+            }
+        }
+
+        for name in failed {
+            if let Some(info) = self.modules.remove(name) {
+                self.unavailable.insert(name, info.module.fields());
+                self.widgets.retain(|(module, _), _| *module != name);
+            }
+        }
+    }
+
     pub fn register_widget<M: Module + Named>(&mut self, name: &'static str, builder: WidgetBuilder) -> Result<()> {
         let id = (M::NAME, name);
         self.widgets.insert(id, builder);
         Ok(())
     }
+
+    /// Retrieves the module registered under the given name.
+    pub fn get(&self, name: &str) -> Option<&dyn Module> {
+        self.modules.get(name).map(|info| info.module.as_ref())
+    }
+
+    /// The fields of the module with the given name, whether it has been loaded or is unavailable.
+    pub fn fields(&self, name: &str) -> Option<&'static [FieldInfo]> {
+        match self.modules.get(name) {
+            Some(info) => Some(info.module.fields()),
+            None => self.unavailable.get(name).copied(),
+        }
+    }
+
+    /// Iterates over every builtin module as `(name, fields, loaded)`, sorted by name,
+    /// including the modules that could not be initialised on this host.
+    pub fn known_modules(&self) -> impl Iterator<Item=(&'static str, &'static [FieldInfo], bool)> {
+        let mut modules = self.modules.values()
+            .map(|info| (info.name, info.module.fields(), true))
+            .chain(self.unavailable.iter().map(|(name, fields)| (*name, *fields, false)))
+            .collect::<Vec<_>>();
+        modules.sort_by_key(|(name, _, _)| *name);
+        modules.into_iter()
+    }
+
+    /// Instantiates the widget registered by the given module under the given name,
+    /// returning it along with the name of the module.
+    pub fn build_widget(&self, module: &str, widget: &str) -> Option<(&'static str, Box<dyn Widget>)> {
+        self.widgets.iter()
+            .find(|((m, w), _)| *m == module && *w == widget)
+            .map(|((module, _), builder)| (*module, builder()))
+    }
+
+    /// Iterates over the names of the registered widgets as `(module, widget)` pairs, sorted by name.
+    pub fn widgets(&self) -> impl Iterator<Item=(&'static str, &'static str)> {
+        let mut widgets = self.widgets.keys().copied().collect::<Vec<_>>();
+        widgets.sort();
+        widgets.into_iter()
+    }
 }
 
-pub fn build_module_registry() -> Result<()> {
+/// Builds a registry containing every builtin module, where those that
+/// could not be initialised are registered as unavailable.
+pub fn build_module_registry() -> Result<ModuleRegistry> {
     let mut registry = ModuleRegistry::new();
 
     SwayModule::register(&mut registry)?;
 
-    Ok(())
+    match BatteryModule::init() {
+        Ok(battery) => registry.register(battery)?,
+        Err(err) => {
+            warn!("Unable to initialise battery module: {}", err);
+            registry.register_unavailable::<BatteryModule>(BatteryModule::FIELDS);
+        }
+    }
+
+    Ok(registry)
 }
\ No newline at end of file
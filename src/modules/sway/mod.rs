@@ -7,7 +7,7 @@ use crate::calloop::generic::{Fd, Generic};
 use crate::calloop::{Interest, Mode, PostAction, RegistrationToken, Readiness};
 use crate::{Module, LoopHandle, SharedLoopData, modules::Result};
 use byteorder::{ReadBytesExt, WriteBytesExt, NativeEndian};
-use crate::modules::{ModuleRegistry, Named};
+use crate::modules::{FieldInfo, ModuleRegistry, Named, ValueKind};
 use crate::error::gather_err;
 
 const MODULE_NAME: &'static str = "core:sway";
@@ -60,7 +60,7 @@ impl SwayModule {
                     IpcMessage::WorkspaceEvent(ipc::WorkspaceEvent { change, current: Some(info), .. })
                     if change == "init" => {
                         module_state.workspaces.push(info);
-                        shared.update();
+                        shared.update(MODULE_NAME);
                     }
 
                     IpcMessage::WorkspaceEvent(ipc::WorkspaceEvent { change, current: Some(info), .. })
@@ -73,7 +73,7 @@ impl SwayModule {
                             }
                             i += 1;
                         }
-                        shared.update();
+                        shared.update(MODULE_NAME);
                     }
 
                     IpcMessage::ShutdownEvent(ipc::ShutdownEvent { reason }) => {}
@@ -92,6 +92,16 @@ impl SwayModule {
 }
 
 impl Module for SwayModule {
+    fn fields(&self) -> &'static [FieldInfo] {
+        &[
+            FieldInfo {
+                name: "focused_view_name",
+                kind: ValueKind::String,
+                description: "The name of the focused view, empty if no view is focused",
+            },
+        ]
+    }
+
     fn write(&self, field: &str, dst: &mut String) -> Result<bool> {
         let is_valid_field = match field {
             "focused_view_name" => {
@@ -0,0 +1,228 @@
+//! Typed values of module fields.
+
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// The value of a module field as read through `Module::read`.
+/// Unlike the text written by `Module::write`, these can be consumed
+/// by widgets directly, such as to draw a gauge or pick a style.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    Float(f64),
+    /// A percentage, where `100.0` is whole.
+    Percentage(f64),
+    Bool(bool),
+    String(String),
+    List(Vec<Value>),
+    Duration(Duration),
+}
+
+/// The type of a `Value`, as declared by the fields of a module.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ValueKind {
+    Integer,
+    Float,
+    Percentage,
+    Bool,
+    String,
+    List,
+    Duration,
+}
+
+impl Display for ValueKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            ValueKind::Integer => "integer",
+            ValueKind::Float => "float",
+            ValueKind::Percentage => "percentage",
+            ValueKind::Bool => "bool",
+            ValueKind::String => "string",
+            ValueKind::List => "list",
+            ValueKind::Duration => "duration",
+        })
+    }
+}
+
+impl Value {
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            Value::Integer(_) => ValueKind::Integer,
+            Value::Float(_) => ValueKind::Float,
+            Value::Percentage(_) => ValueKind::Percentage,
+            Value::Bool(_) => ValueKind::Bool,
+            Value::String(_) => ValueKind::String,
+            Value::List(_) => ValueKind::List,
+            Value::Duration(_) => ValueKind::Duration,
+        }
+    }
+
+    /// The numeric value, if there is one. Durations are given in seconds.
+    /// Strings have none, even if they hold a number.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Integer(value) => Some(*value as f64),
+            Value::Float(value) | Value::Percentage(value) => Some(*value),
+            Value::Duration(duration) => Some(duration.as_secs_f64()),
+            Value::Bool(_) | Value::String(_) | Value::List(_) => None,
+        }
+    }
+
+    /// The value as a fraction of a whole, clamped to `0.0..=1.0`.
+    /// Only percentages and floats, which are taken to be fractions already, have one.
+    pub fn as_fraction(&self) -> Option<f64> {
+        match self {
+            Value::Percentage(value) => Some((value / 100.0).clamp(0.0, 1.0)),
+            Value::Float(value) => Some(value.clamp(0.0, 1.0)),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Whether the value is an empty string or list.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Value::String(value) => value.is_empty(),
+            Value::List(values) => values.is_empty(),
+            _ => false,
+        }
+    }
+}
+
+/// Values are displayed as they would be written by `Module::write`. Percentages are
+/// displayed without a percent sign, lists are separated by commas and durations
+/// are displayed as `h:mm:ss`, or `m:ss` if less than an hour.
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Integer(value) => write!(f, "{}", value),
+            Value::Float(value) | Value::Percentage(value) => write!(f, "{}", value),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::String(value) => write!(f, "{}", value),
+            Value::List(values) => {
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                Ok(())
+            }
+            Value::Duration(duration) => {
+                let secs = duration.as_secs();
+                let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+                if hours > 0 {
+                    write!(f, "{}:{:02}:{:02}", hours, minutes, secs)
+                } else {
+                    write!(f, "{}:{:02}", minutes, secs)
+                }
+            }
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<Duration> for Value {
+    fn from(value: Duration) -> Self {
+        Value::Duration(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Result;
+    use crate::modules::{FieldInfo, Module};
+
+    #[test]
+    fn durations_are_displayed_as_minutes_or_hours() {
+        assert_eq!(Value::Duration(Duration::from_secs(0)).to_string(), "0:00");
+        assert_eq!(Value::Duration(Duration::from_secs(65)).to_string(), "1:05");
+        assert_eq!(Value::Duration(Duration::from_secs(3599)).to_string(), "59:59");
+        assert_eq!(Value::Duration(Duration::from_secs(3600)).to_string(), "1:00:00");
+        assert_eq!(Value::Duration(Duration::from_millis(37_384_900)).to_string(), "10:23:04");
+    }
+
+    #[test]
+    fn lists_are_separated_by_commas() {
+        assert_eq!(Value::List(Vec::new()).to_string(), "");
+        assert_eq!(Value::List(vec![Value::Integer(1)]).to_string(), "1");
+
+        let list = Value::List(vec![Value::from("a".to_owned()), Value::Percentage(50.0), Value::from(true)]);
+        assert_eq!(list.to_string(), "a, 50, true");
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(Value::from(3).as_f64(), Some(3.0));
+        assert_eq!(Value::from(Duration::from_millis(1500)).as_f64(), Some(1.5));
+        assert_eq!(Value::from("3".to_owned()).as_f64(), None);
+
+        assert_eq!(Value::Percentage(50.0).as_fraction(), Some(0.5));
+        assert_eq!(Value::Percentage(120.0).as_fraction(), Some(1.0));
+        assert_eq!(Value::from(0.25).as_fraction(), Some(0.25));
+        assert_eq!(Value::from(3).as_fraction(), None);
+
+        assert_eq!(Value::from(true).as_bool(), Some(true));
+        assert_eq!(Value::from("x".to_owned()).as_str(), Some("x"));
+        assert_eq!(Value::from(1).as_str(), None);
+    }
+
+    struct Stub;
+
+    impl Module for Stub {
+        fn fields(&self) -> &'static [FieldInfo] {
+            &[]
+        }
+
+        fn write(&self, field: &str, dst: &mut String) -> Result<bool> {
+            match field {
+                "name" => dst.push_str("owl"),
+                _ => return Ok(false),
+            }
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn modules_read_their_written_text_by_default() {
+        assert_eq!(Stub.read("name").unwrap(), Some(Value::String("owl".to_owned())));
+        assert_eq!(Stub.read("missing").unwrap(), None);
+    }
+}
@@ -0,0 +1,18 @@
+//! Client bindings for wayland protocols that are not provided by
+//! the version of `wayland-protocols` that we depend on.
+//! The bindings are generated from the definitions in `protocols/` by the build script.
+
+#![allow(dead_code, non_camel_case_types, unused_unsafe, unused_variables)]
+#![allow(non_upper_case_globals, non_snake_case, unused_imports, static_mut_refs)]
+#![allow(missing_docs, clippy::all)]
+
+pub mod fractional_scale_v1 {
+    pub(crate) use wayland_client::{Main, Attached, Proxy, ProxyMap, AnonymousObject};
+    pub(crate) use wayland_commons::map::{Object, ObjectMetadata};
+    pub(crate) use wayland_commons::{Interface, MessageGroup};
+    pub(crate) use wayland_commons::wire::{Argument, MessageDesc, ArgumentType, Message};
+    pub(crate) use wayland_commons::smallvec;
+    pub(crate) use wayland_client::protocol::wl_surface;
+    pub(crate) use wayland_client::sys;
+    include!(concat!(env!("OUT_DIR"), "/fractional_scale_v1_client_api.rs"));
+}
@@ -0,0 +1,103 @@
+//! Styling of the bar, resolved from the theme and the styles of widgets and passed to widgets.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+use piet::{Color, FontFamily, FontWeight};
+use crate::config::{self, StyleConfig, StyleRef, ThemeConfig};
+use crate::error::{Error, Kind, Result};
+
+/// The number of named styles that a style may extend in a chain,
+/// beyond which the styles are assumed to extend each other in a cycle.
+const MAX_EXTENDS: usize = 16;
+
+/// The styling with which widgets are drawn.
+#[derive(Clone)]
+pub struct Theme {
+    pub font_family: FontFamily,
+    pub font_size: f64,
+    pub font_weight: FontWeight,
+    pub foreground: Color,
+    pub background: Color,
+    pub accent: Color,
+    pub urgent: Color,
+    pub inactive: Color,
+    pub border_radius: f64,
+    pub padding: f64,
+    styles: Rc<HashMap<String, StyleConfig>>,
+}
+
+impl Theme {
+    /// Resolves the theme of a bar, whose colors take precedence over those of the theme.
+    pub fn new(config: &ThemeConfig, bar: &config::Bar) -> Self {
+        Theme {
+            font_family: font_family(&config.font_family),
+            font_size: config.font_size,
+            font_weight: FontWeight::new(config.font_weight),
+            foreground: bar.foreground.unwrap_or(config.foreground).into(),
+            background: bar.background.unwrap_or(config.background).into(),
+            accent: config.accent.into(),
+            urgent: config.urgent.into(),
+            inactive: config.inactive.into(),
+            border_radius: config.border_radius,
+            padding: config.padding,
+            styles: Rc::new(config.styles.clone()),
+        }
+    }
+
+    /// The theme with the given style applied on top. Fails if the style refers
+    /// to a named style that does not exist, or if named styles extend each other in a cycle.
+    pub fn with_style(&self, style: &StyleRef) -> Result<Theme> {
+        match style {
+            StyleRef::Named(name) => self.with_named(name, 0),
+            StyleRef::Inline(style) => self.with_overrides(style, 0),
+        }
+    }
+
+    fn with_named(&self, name: &str, depth: usize) -> Result<Theme> {
+        if depth >= MAX_EXTENDS {
+            return Err(Error::new(Kind::ConfigError)
+                .with_msg(format!("style `{}` extends too many styles, or styles extend each other in a cycle", name)));
+        }
+
+        match self.styles.get(name) {
+            Some(style) => self.with_overrides(style, depth + 1),
+            None => Err(Error::new(Kind::ConfigError).with_msg(format!("there is no style named `{}`", name))),
+        }
+    }
+
+    fn with_overrides(&self, style: &StyleConfig, depth: usize) -> Result<Theme> {
+        let mut theme = match &style.extends {
+            Some(name) => self.with_named(name, depth)?,
+            None => self.clone(),
+        };
+
+        if let Some(family) = &style.font_family { theme.font_family = font_family(family); }
+        if let Some(size) = style.font_size { theme.font_size = size; }
+        if let Some(weight) = style.font_weight { theme.font_weight = FontWeight::new(weight); }
+        if let Some(color) = style.foreground { theme.foreground = color.into(); }
+        if let Some(color) = style.background { theme.background = color.into(); }
+        if let Some(color) = style.accent { theme.accent = color.into(); }
+        if let Some(color) = style.urgent { theme.urgent = color.into(); }
+        if let Some(color) = style.inactive { theme.inactive = color.into(); }
+        if let Some(radius) = style.border_radius { theme.border_radius = radius; }
+        if let Some(padding) = style.padding { theme.padding = padding; }
+
+        Ok(theme)
+    }
+
+    /// The names of the styles defined by the theme, in no particular order.
+    pub fn styles(&self) -> impl Iterator<Item = &str> {
+        self.styles.keys().map(String::as_str)
+    }
+}
+
+/// The font family with the given name, mapping the names of generic families onto them.
+fn font_family(name: &str) -> FontFamily {
+    match name {
+        "sans-serif" => FontFamily::SANS_SERIF,
+        "serif" => FontFamily::SERIF,
+        "monospace" => FontFamily::MONOSPACE,
+        "system-ui" => FontFamily::SYSTEM_UI,
+        name => FontFamily::new_unchecked(name),
+    }
+}
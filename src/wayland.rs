@@ -1,39 +1,98 @@
 use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::f64::consts::FRAC_PI_2;
 use std::marker::PhantomData;
 use std::rc::Rc;
+use piet::RenderContext;
+use piet::kurbo::{Affine, Rect, Size, Vec2};
 use piet_common::CairoRenderContext;
 use smithay_client_toolkit::{
+    data_device::DataDeviceHandler,
     default_environment,
     environment::SimpleGlobal,
-    new_default_environment,
-    output::{with_output_info, OutputInfo},
+    output::{add_output_listener, with_output_info, OutputInfo, OutputListener, XdgOutputHandler},
+    primary_selection::PrimarySelectionHandler,
     reexports::{
         calloop,
-        client::protocol::{wl_output, wl_shm, wl_surface},
-        client::{Attached, Main},
+        client::protocol::{wl_callback, wl_output, wl_shm, wl_surface},
+        client::{Attached, Main, Proxy},
+        protocols::unstable::xdg_output::v1::client::zxdg_output_manager_v1::ZxdgOutputManagerV1,
+        protocols::viewporter::client::{wp_viewport, wp_viewporter},
         protocols::wlr::unstable::layer_shell::v1::client as wlr_client,
         protocols::wlr::unstable::layer_shell::v1::client::{
             zwlr_layer_shell_v1, zwlr_layer_surface_v1,
         },
     },
-    shm::AutoMemPool,
+    seat::SeatHandler,
+    shm::{AutoMemPool, ShmHandler},
     WaylandSource,
 };
 use smithay_client_toolkit::environment::Environment;
 use smithay_client_toolkit::output::OutputStatusListener;
 use smithay_client_toolkit::reexports::client::{Display, EventQueue};
-use crate::error::{Result, Error, Kind};
-use crate::LoopHandle;
+use log::{error, warn};
+use crate::config;
+use crate::protocols::fractional_scale_v1::{wp_fractional_scale_manager_v1, wp_fractional_scale_v1};
+use crate::error::{Result, Error, Kind, Severity};
+use crate::layout::{self, Group, Span};
+use crate::modules::{ModuleRegistry, Widget, WidgetContext};
+use crate::theme::Theme;
+use crate::{Dirty, LoopHandle, Modules};
+use crate::widgets::{self, TextWidget};
 
 default_environment!(Env,
     fields = [
         layer_shell: SimpleGlobal<zwlr_layer_shell_v1::ZwlrLayerShellV1>,
+        xdg_output: XdgOutputHandler,
+        viewporter: SimpleGlobal<wp_viewporter::WpViewporter>,
+        fractional_scale_manager: SimpleGlobal<wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1>,
     ],
     singles = [
-        zwlr_layer_shell_v1::ZwlrLayerShellV1 => layer_shell
+        zwlr_layer_shell_v1::ZwlrLayerShellV1 => layer_shell,
+        ZxdgOutputManagerV1 => xdg_output,
+        wp_viewporter::WpViewporter => viewporter,
+        wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1 => fractional_scale_manager,
     ],
 );
 
+/// Connects to the wayland display and sets up the environment.
+///
+/// The output handler has to be bound to an `XdgOutputHandler` for the names of outputs
+/// to be known, which `new_default_environment!` does not allow for. Hence the environment
+/// is assembled by hand here, mirroring what said macro does.
+fn new_environment() -> Result<(Environment<Env>, Display, EventQueue)> {
+    let display = Display::connect_to_env()
+        .map_err(|err| Error::new(Kind::WaylandError)
+            .with_msg(format!("Unable to connect to the wayland display: {}", err)))?;
+    let mut queue = display.create_event_queue();
+
+    let (sctk_outputs, xdg_output) = XdgOutputHandler::new_output_handlers();
+    let mut sctk_seats = SeatHandler::new();
+    let sctk_data_device_manager = DataDeviceHandler::init(&mut sctk_seats);
+    let sctk_primary_selection_manager = PrimarySelectionHandler::init(&mut sctk_seats);
+
+    let attached_display = Proxy::clone(&display).attach(queue.token());
+    let env = Environment::new(&attached_display, &mut queue, Env {
+        sctk_compositor: SimpleGlobal::new(),
+        sctk_subcompositor: SimpleGlobal::new(),
+        sctk_shm: ShmHandler::new(),
+        sctk_outputs,
+        sctk_seats,
+        sctk_data_device_manager,
+        sctk_primary_selection_manager,
+        layer_shell: SimpleGlobal::new(),
+        xdg_output,
+        viewporter: SimpleGlobal::new(),
+        fractional_scale_manager: SimpleGlobal::new(),
+    })?;
+
+    // Make sure that the xdg output information of the initial outputs has arrived
+    queue.sync_roundtrip(&mut (), |_, _, _| unreachable!())?;
+
+    Ok((env, display, queue))
+}
+
 /// A wrapper structure intended to facilitate drawing directly
 /// to the wayland surface buffer.
 struct PietWaylandSurface<'a> {
@@ -67,6 +126,12 @@ impl<'a> PietWaylandSurface<'a> {
     fn get_context(&mut self) -> CairoRenderContext {
         CairoRenderContext::new(&self.context)
     }
+
+    /// Ensures that all pending drawing operations have been written
+    /// to the underlying canvas.
+    fn flush(&self) {
+        self.image_surface.flush();
+    }
 }
 
 #[derive(PartialEq, Copy, Clone)]
@@ -81,12 +146,392 @@ pub enum SurfaceAction {
     None,
 }
 
+/// The direction along which the widgets of a bar are laid out.
+#[derive(Copy, Clone, PartialEq)]
+enum Axis {
+    /// Left-to-right, as on bars anchored to the top or bottom of an output.
+    Horizontal,
+    /// Top-to-bottom, as on bars anchored to the left or right of an output.
+    Vertical,
+}
+
+impl Axis {
+    fn of(config: &config::Bar) -> Self {
+        if config.anchor.is_horizontal() { Axis::Horizontal } else { Axis::Vertical }
+    }
+
+    /// The length of the given size along the axis.
+    fn length(self, size: Size) -> f64 {
+        match self {
+            Axis::Horizontal => size.width,
+            Axis::Vertical => size.height,
+        }
+    }
+
+    /// The part of the bounds covered by the span along the axis.
+    fn rect(self, bounds: Rect, span: Span) -> Rect {
+        match self {
+            Axis::Horizontal => Rect::new(bounds.x0 + span.start, bounds.y0, bounds.x0 + span.end(), bounds.y1),
+            Axis::Vertical => Rect::new(bounds.x0, bounds.y0 + span.start, bounds.x1, bounds.y0 + span.end()),
+        }
+    }
+
+    /// Shrinks the rectangle by `padding` at either end along the axis.
+    fn inset(self, rect: Rect, padding: f64) -> Rect {
+        match self {
+            Axis::Horizontal => Rect::new(rect.x0 + padding, rect.y0, rect.x1 - padding, rect.y1),
+            Axis::Vertical => Rect::new(rect.x0, rect.y0 + padding, rect.x1, rect.y1 - padding),
+        }
+    }
+}
+
+/// The scale at which the contents of a surface are rendered.
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Scale {
+    /// The integer scale of the output, applied through `wl_surface.set_buffer_scale`.
+    Integer(i32),
+    /// The scale preferred by the compositor in 120ths, applied through a viewport.
+    Fractional(u32),
+}
+
+impl Scale {
+    fn factor(self) -> f64 {
+        match self {
+            Scale::Integer(scale) => scale as f64,
+            Scale::Fractional(scale) => scale as f64 / 120.0,
+        }
+    }
+
+    /// The size of the buffer that covers a surface of the given size at this scale.
+    /// Fractionally scaled sizes are rounded half away from zero, as the protocol asks.
+    fn buffer_size(self, (width, height): (u32, u32)) -> (i32, i32) {
+        match self {
+            Scale::Integer(scale) => (width as i32 * scale, height as i32 * scale),
+            Scale::Fractional(_) => (
+                (width as f64 * self.factor()).round() as i32,
+                (height as f64 * self.factor()).round() as i32,
+            ),
+        }
+    }
+}
+
+/// The globals needed for rendering surfaces at fractional scales.
+/// Only available if the compositor supports both of them.
+#[derive(Clone)]
+struct FractionalScaling {
+    viewporter: Attached<wp_viewporter::WpViewporter>,
+    manager: Attached<wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1>,
+}
+
+/// The per-surface objects of `FractionalScaling`.
+struct FractionalScale {
+    viewport: Main<wp_viewport::WpViewport>,
+    fractional_scale: Main<wp_fractional_scale_v1::WpFractionalScaleV1>,
+    /// The most recent scale preferred by the compositor, in 120ths.
+    preferred: Rc<Cell<Option<u32>>>,
+}
+
+impl FractionalScale {
+    fn new(surface: &wl_surface::WlSurface, scaling: &FractionalScaling) -> Self {
+        let viewport = scaling.viewporter.get_viewport(surface);
+        let fractional_scale = scaling.manager.get_fractional_scale(surface);
+
+        let preferred = Rc::new(Cell::new(None));
+        let preferred_handle = Rc::clone(&preferred);
+        fractional_scale.quick_assign(move |_, event, _| {
+            let wp_fractional_scale_v1::Event::PreferredScale { scale } = event;
+            preferred_handle.set(Some(scale));
+        });
+
+        FractionalScale { viewport, fractional_scale, preferred }
+    }
+}
+
+/// How the widgets of a bar are oriented.
+struct Style {
+    axis: Axis,
+    rotated: bool,
+}
+
+impl Style {
+    fn of(config: &config::Bar) -> Self {
+        let axis = Axis::of(config);
+        Style {
+            axis,
+            rotated: axis == Axis::Vertical && config.rotate_text,
+        }
+    }
+
+    /// The space taken up on the bar by the widget.
+    fn extent(&self, rc: &mut CairoRenderContext, widget: &dyn Widget, ctx: &WidgetContext) -> Result<Size> {
+        let size = widget.compute_dimensions(rc, ctx)?;
+        Ok(if self.rotated { Size::new(size.height, size.width) } else { size })
+    }
+
+    /// Paints the widget over the background of its theme in `rect`, leaving `padding` free at either end.
+    /// Drawing is clipped to the remainder. Rotated widgets are turned 90° clockwise, such that
+    /// text reads from top to bottom.
+    fn paint(
+        &self,
+        rc: &mut CairoRenderContext,
+        widget: &dyn Widget,
+        rect: Rect,
+        padding: f64,
+        ctx: &WidgetContext,
+    ) -> Result<()> {
+        rc.fill(rect, &ctx.theme.background);
+
+        let inner = self.axis.inset(rect, padding);
+        rc.save()?;
+        rc.clip(inner);
+        let result = if self.rotated {
+            rc.transform(Affine::translate(Vec2::new(inner.x1, inner.y0)) * Affine::rotate(FRAC_PI_2));
+            widget.draw(rc, Rect::new(0.0, 0.0, inner.height(), inner.width()), ctx)
+        } else {
+            widget.draw(rc, inner, ctx)
+        };
+        rc.restore()?;
+        result
+    }
+}
+
+/// A rectangle of the bar in which a single widget is displayed.
+struct Region {
+    widget: Box<dyn Widget>,
+    /// The module that registered the widget, `None` for builtin widgets.
+    module: Option<&'static str>,
+    group: Group,
+    placement: config::Placement,
+    /// The theme of the bar with the style of the widget applied.
+    theme: Theme,
+    /// The length that the widget asked for when the bar was last laid out.
+    length: f64,
+    /// Empty if the widget has been hidden.
+    rect: Rect,
+}
+
+impl Region {
+    fn new(instance: widgets::Instance, group: Group, placement: config::Placement, theme: Theme) -> Self {
+        Region {
+            widget: instance.widget,
+            module: instance.module,
+            group,
+            placement,
+            theme,
+            length: 0.0,
+            rect: Rect::ZERO,
+        }
+    }
+
+    fn depends_on(&self, module: &str) -> bool {
+        self.module == Some(module) || self.widget.depends_on(module)
+    }
+
+    fn context<'a>(&'a self, registry: &'a ModuleRegistry) -> WidgetContext<'a> {
+        WidgetContext {
+            module: self.module.and_then(|name| registry.get(name)),
+            registry,
+            theme: &self.theme,
+        }
+    }
+
+    fn padding(&self) -> f64 {
+        self.placement.padding.unwrap_or(self.theme.padding)
+    }
+
+    /// The length along the bar that the widget asks for, including its padding
+    /// and limited to its minimum and maximum width.
+    fn measure(&self, rc: &mut CairoRenderContext, registry: &ModuleRegistry, style: &Style) -> Result<f64> {
+        let extent = style.extent(rc, self.widget.as_ref(), &self.context(registry))?;
+        let length = style.axis.length(extent) + 2.0 * self.padding();
+        let length = self.placement.min_width.map_or(length, |min| length.max(min));
+        Ok(self.placement.max_width.map_or(length, |max| length.min(max)))
+    }
+
+    fn paint(&self, rc: &mut CairoRenderContext, registry: &ModuleRegistry, style: &Style) -> Result<()> {
+        style.paint(rc, self.widget.as_ref(), self.rect, self.padding(), &self.context(registry))
+    }
+}
+
+/// The widgets of a bar, along with the separators between them.
+struct Contents {
+    /// The theme of the bar, which widgets without a style of their own are drawn with.
+    theme: Theme,
+    regions: Vec<Region>,
+    separator: Option<TextWidget>,
+    separator_rects: Vec<Rect>,
+}
+
+impl Contents {
+    /// Creates a region for every widget of the bar. Widgets that are unknown or fail
+    /// to be configured are left out, while those whose style cannot be resolved
+    /// fall back to the theme of the bar.
+    fn new(config: &config::Bar, theme: Theme, modules: &Modules) -> Self {
+        let separator = config.separator.as_deref().map(TextWidget::literal);
+        let registry = match modules.read() {
+            Ok(registry) => registry,
+            Err(_) => return Contents { theme, regions: Vec::new(), separator, separator_rects: Vec::new() },
+        };
+
+        let regions = [(Group::Left, &config.left), (Group::Center, &config.center), (Group::Right, &config.right)]
+            .into_iter()
+            .flat_map(|(group, widgets)| widgets.iter().map(move |widget| (group, widget)))
+            .filter_map(|(group, widget)| match widgets::build(widget, &registry) {
+                Ok(instance) => {
+                    let style = widget.style.as_ref().map(|style| theme.with_style(style));
+                    let theme = match style {
+                        Some(Ok(styled)) => styled,
+                        Some(Err(err)) => {
+                            warn!("Unable to apply the style of widget `{}`: {}", widget, err);
+                            theme.clone()
+                        }
+                        None => theme.clone(),
+                    };
+                    Some(Region::new(instance, group, widget.placement, theme))
+                }
+                Err(err) => {
+                    error!("Unable to create widget `{}`: {}", widget, err);
+                    None
+                }
+            })
+            .collect();
+
+        Contents { theme, regions, separator, separator_rects: Vec::new() }
+    }
+
+    /// Repaints the regions belonging to the modules marked as dirty, returning
+    /// the rectangles that have been repainted. The entire bar is laid out and
+    /// repainted if a widget asks for a different length than before.
+    fn paint(
+        &mut self,
+        rc: &mut CairoRenderContext,
+        registry: &ModuleRegistry,
+        config: &config::Bar,
+        dirty: &Dirty,
+        bounds: Rect,
+    ) -> Result<Vec<Rect>> {
+        let style = Style::of(config);
+
+        if let Dirty::Modules(changed) = dirty {
+            let dirty_regions = self.regions.iter()
+                .filter(|region| changed.iter().any(|module| region.depends_on(module)))
+                .collect::<Vec<_>>();
+
+            let mut unchanged = true;
+            for region in &dirty_regions {
+                unchanged &= region.measure(rc, registry, &style)? == region.length;
+            }
+
+            if unchanged {
+                return dirty_regions.into_iter()
+                    .filter(|region| region.rect.area() > 0.0)
+                    .map(|region| {
+                        region.paint(rc, registry, &style)?;
+                        Ok(region.rect)
+                    })
+                    .collect();
+            }
+        }
+
+        self.layout(rc, registry, config, &style, bounds)?;
+
+        rc.fill(bounds, &self.theme.background);
+        for region in self.regions.iter().filter(|region| region.rect.area() > 0.0) {
+            region.paint(rc, registry, &style)?;
+        }
+        if let Some(separator) = &self.separator {
+            let ctx = WidgetContext { module: None, registry, theme: &self.theme };
+            for rect in &self.separator_rects {
+                style.paint(rc, separator, *rect, 0.0, &ctx)?;
+            }
+        }
+
+        Ok(vec![bounds])
+    }
+
+    /// Measures the widgets and arranges them into their groups along the bar.
+    fn layout(
+        &mut self,
+        rc: &mut CairoRenderContext,
+        registry: &ModuleRegistry,
+        config: &config::Bar,
+        style: &Style,
+        bounds: Rect,
+    ) -> Result<()> {
+        for region in self.regions.iter_mut() {
+            region.length = region.measure(rc, registry, style)?;
+        }
+
+        let separator = match &self.separator {
+            Some(separator) => {
+                let ctx = WidgetContext { module: None, registry, theme: &self.theme };
+                Some(style.axis.length(style.extent(rc, separator, &ctx)?))
+            }
+            None => None,
+        };
+
+        let items = self.regions.iter()
+            .map(|region| layout::Item {
+                group: region.group,
+                length: region.length,
+                min_length: region.placement.min_width.unwrap_or(0.0),
+                priority: region.placement.priority,
+            })
+            .collect::<Vec<_>>();
+
+        let options = layout::Options {
+            spacing: config.spacing,
+            padding: config.padding,
+            separator,
+            overflow: config.overflow,
+        };
+
+        let arrangement = layout::arrange(&items, style.axis.length(bounds.size()), &options);
+        for (region, span) in self.regions.iter_mut().zip(arrangement.items) {
+            region.rect = span.map_or(Rect::ZERO, |span| style.axis.rect(bounds, span));
+        }
+        self.separator_rects = arrangement.separators.into_iter()
+            .map(|span| style.axis.rect(bounds, span))
+            .collect();
+
+        Ok(())
+    }
+}
+
+/// What a surface is created with, besides the wayland objects that make it up.
+struct SurfaceOptions<'a> {
+    config: Rc<config::Bar>,
+    theme: Rc<config::ThemeConfig>,
+    modules: Modules,
+    /// The integer scale of the output that the surface is placed on.
+    output_scale: i32,
+    /// Present if the compositor supports rendering at fractional scales.
+    fractional_scaling: Option<&'a FractionalScaling>,
+}
+
 pub struct Surface {
     surface: wl_surface::WlSurface,
     layer_surface: Main<zwlr_layer_surface_v1::ZwlrLayerSurfaceV1>,
     next_event: Rc<Cell<Option<SurfaceEvent>>>,
     pool: AutoMemPool,
+    /// Copy of the most recently drawn frame. Buffers handed out by the pool
+    /// carry no prior content, so partial redraws are painted here first.
+    back_buffer: Vec<u8>,
+    /// The size of the surface, in surface-local coordinates.
     dimensions: (u32, u32),
+    /// The integer scale of the output that the surface is placed on.
+    output_scale: i32,
+    /// Present if the surface is rendered at the fractional scale preferred by the compositor.
+    fractional_scale: Option<FractionalScale>,
+    /// The scale at which the back buffer has been drawn.
+    scale: Scale,
+    config: Rc<config::Bar>,
+    theme: Rc<config::ThemeConfig>,
+    modules: Modules,
+    contents: Contents,
+    /// Set while a frame callback requested by the last draw has yet to be received.
+    frame_pending: Rc<Cell<bool>>,
+    pending_redraw: Option<Dirty>,
 }
 
 impl Surface {
@@ -95,12 +540,14 @@ impl Surface {
         surface: wl_surface::WlSurface,
         layer_shell: &Attached<zwlr_layer_shell_v1::ZwlrLayerShellV1>,
         pool: AutoMemPool,
+        options: SurfaceOptions,
     ) -> Self {
+        let SurfaceOptions { config, theme, modules, output_scale, fractional_scaling } = options;
         let layer_surface = layer_shell.get_layer_surface(
             &surface,
             Some(output),
-            zwlr_layer_shell_v1::Layer::Top,
-            "owl-rs".to_owned(),
+            config.layer.into(),
+            config.namespace.clone(),
         );
 
         let next_event = Rc::new(Cell::new(None::<SurfaceEvent>));
@@ -121,25 +568,231 @@ impl Surface {
             }
         });
 
-        // Commit so that the server will send a configure event
-        surface.commit();
+        let contents = Contents::new(&config, Theme::new(&theme, &config), &modules);
+        let fractional_scale = fractional_scaling
+            .filter(|_| config.fractional_scale)
+            .map(|scaling| FractionalScale::new(&surface, scaling));
 
-        Self { surface, layer_surface, next_event, pool, dimensions: (0, 0) }
+        let mut surface = Self {
+            surface,
+            layer_surface,
+            next_event,
+            pool,
+            back_buffer: Vec::new(),
+            dimensions: (0, 0),
+            output_scale,
+            fractional_scale,
+            scale: Scale::Integer(output_scale.max(1)),
+            config,
+            theme,
+            modules,
+            contents,
+            frame_pending: Rc::new(Cell::new(false)),
+            pending_redraw: None,
+        };
+        surface.configure();
+        surface
     }
 
+    /// Applies the size, anchor, margin, exclusive zone and keyboard interactivity
+    /// of the bar to the layer surface. The surface is committed afterwards such
+    /// that the server will respond with a configure event.
     pub fn configure(&mut self) {
-        todo!()
+        let margin = self.config.margin;
+        let (width, height) = self.config.size();
+        self.layer_surface.set_size(width, height);
+        self.layer_surface.set_anchor(self.config.anchor.into());
+        self.layer_surface.set_margin(margin.top, margin.right, margin.bottom, margin.left);
+        self.layer_surface.set_exclusive_zone(self.config.exclusive_zone());
+        self.layer_surface.set_keyboard_interactivity(self.config.keyboard_interactivity.into());
+
+        // Commit so that the server will send a configure event
+        self.surface.commit();
+    }
+
+    pub fn config(&self) -> &config::Bar {
+        &self.config
+    }
+
+    pub fn theme(&self) -> &config::ThemeConfig {
+        &self.theme
+    }
+
+    /// Whether the given configuration can be applied to the existing layer surface.
+    /// The layer and namespace are fixed once the layer surface has been created,
+    /// as is whether the surface is fractionally scaled.
+    pub fn can_reconfigure(&self, config: &config::Bar) -> bool {
+        self.config.layer == config.layer
+            && self.config.namespace == config.namespace
+            && self.config.fractional_scale == config.fractional_scale
+    }
+
+    /// Replaces the configuration and theme of the surface, re-applying the configuration
+    /// to the layer surface and scheduling a full redraw.
+    pub fn reconfigure(&mut self, config: Rc<config::Bar>, theme: Rc<config::ThemeConfig>) {
+        self.contents = Contents::new(&config, Theme::new(&theme, &config), &self.modules);
+        self.config = config;
+        self.theme = theme;
+        self.configure();
+        self.request_redraw(Dirty::All);
+    }
+
+    /// Updates the integer scale of the output that the surface is placed on.
+    /// The surface is redrawn at the new scale unless it is fractionally scaled.
+    pub fn set_output_scale(&mut self, scale: i32) {
+        self.output_scale = scale;
+        if self.current_scale() != self.scale {
+            self.request_redraw(Dirty::All);
+        }
+    }
+
+    /// The scale at which the surface should currently be drawn.
+    fn current_scale(&self) -> Scale {
+        match self.fractional_scale.as_ref().and_then(|fractional| fractional.preferred.get()) {
+            Some(preferred) => Scale::Fractional(preferred),
+            None => Scale::Integer(self.output_scale.max(1)),
+        }
+    }
+
+    /// Marks the given parts of the surface as needing to be redrawn. The redraw itself
+    /// is deferred until the compositor signals that it is ready for a new frame.
+    pub fn request_redraw(&mut self, dirty: Dirty) {
+        self.pending_redraw = Some(match self.pending_redraw.take() {
+            Some(pending) => pending.merge(dirty),
+            None => dirty,
+        });
     }
 
+    /// Handles the event that has occurred since the last call, redrawing if needed.
     pub fn handle_events(&mut self) -> SurfaceAction {
-        todo!();
-        SurfaceAction::None
+        match self.next_event.take() {
+            Some(SurfaceEvent::Closed) => return SurfaceAction::Drop,
+            Some(SurfaceEvent::Configure { width, height }) => {
+                // The server leaves it to us to pick a size when it sends a zero dimension,
+                // otherwise its size takes precedence over the one that we requested.
+                let (requested_width, requested_height) = self.config.size();
+                let width = if width == 0 { requested_width } else { width };
+                let height = if height == 0 { requested_height } else { height };
+
+                if self.dimensions != (width, height) {
+                    self.dimensions = (width, height);
+                    self.request_redraw(Dirty::All);
+                }
+            }
+            None => {}
+        }
+
+        let scale = self.current_scale();
+        if self.scale != scale {
+            self.scale = scale;
+            self.request_redraw(Dirty::All);
+        }
+
+        // Nothing can be drawn before the first configure event has given the surface a size.
+        if self.frame_pending.get() || self.dimensions.0 == 0 || self.dimensions.1 == 0 {
+            return SurfaceAction::None;
+        }
+
+        match self.pending_redraw.take() {
+            Some(dirty) => match self.draw(dirty) {
+                Ok(()) => SurfaceAction::Redraw,
+                Err(err) => {
+                    error!("Unable to draw surface: {}", err);
+                    SurfaceAction::None
+                }
+            },
+            None => SurfaceAction::None,
+        }
     }
 
+    fn draw(&mut self, mut dirty: Dirty) -> Result<()> {
+        let (width, height) = self.scale.buffer_size(self.dimensions);
+        let factor = self.scale.factor();
+        let stride = 4 * width;
+        let len = (stride * height) as usize;
+
+        if self.back_buffer.len() != len {
+            self.back_buffer.resize(len, 0);
+            dirty = Dirty::All;
+        }
+
+        let damage = {
+            let registry = self.modules.read()
+                .map_err(|_| Error::new(Kind::ModuleError(Severity::Fatal))
+                    .with_msg("The module registry has been poisoned"))?;
+            let mut piet_surface = PietWaylandSurface::new(&mut self.back_buffer, width, height, stride);
+            let mut rc = piet_surface.get_context();
+
+            // Everything is painted in surface-local coordinates, which the transform maps
+            // onto the buffer. The bounds are derived from the buffer such that they cover it
+            // entirely, even if its size has been rounded.
+            rc.transform(Affine::scale(factor));
+            let bounds = Rect::new(0.0, 0.0, width as f64 / factor, height as f64 / factor);
+            let damage = self.contents.paint(&mut rc, &registry, &self.config, &dirty, bounds)?;
+
+            rc.finish()?;
+            drop(rc);
+            piet_surface.flush();
+            damage
+        };
+
+        if damage.is_empty() {
+            return Ok(());
+        }
+
+        // Grow the pool up front such that it does not have to be resized
+        // while allocating the buffer.
+        self.pool.resize(len)?;
+        let (canvas, buffer) =
+            self.pool.buffer(width, height, stride, wl_shm::Format::Argb8888)?;
+        canvas.copy_from_slice(&self.back_buffer);
+
+        // Tell the compositor how the buffer maps onto the surface
+        match self.scale {
+            Scale::Integer(scale) => self.surface.set_buffer_scale(scale),
+            Scale::Fractional(_) => {
+                self.surface.set_buffer_scale(1);
+                if let Some(fractional) = &self.fractional_scale {
+                    let (width, height) = self.dimensions;
+                    fractional.viewport.set_destination(width as i32, height as i32);
+                }
+            }
+        }
+
+        // Attach the buffer to the surface and mark the repainted areas as damaged
+        self.surface.attach(Some(&buffer), 0, 0);
+        for rect in damage {
+            let rect = Affine::scale(factor).transform_rect_bbox(rect).expand();
+            self.surface.damage_buffer(
+                rect.x0 as i32,
+                rect.y0 as i32,
+                rect.width() as i32,
+                rect.height() as i32,
+            );
+        }
+
+        // Request a frame callback such that further redraws are paced by the compositor
+        let frame_pending = Rc::clone(&self.frame_pending);
+        self.surface.frame().quick_assign(move |_, event, _| {
+            if let wl_callback::Event::Done { .. } = event {
+                frame_pending.set(false);
+            }
+        });
+        self.frame_pending.set(true);
+
+        // Finally, commit the surface
+        self.surface.commit();
+
+        Ok(())
+    }
 }
 
 impl Drop for Surface {
     fn drop(&mut self) {
+        if let Some(fractional) = &self.fractional_scale {
+            fractional.fractional_scale.destroy();
+            fractional.viewport.destroy();
+        }
         self.layer_surface.destroy();
         self.surface.destroy();
     }
@@ -159,39 +812,120 @@ impl UnhandledWaylandContext {
     }
 }
 
+/// Identifies the surface of a single bar on a single output.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SurfaceId {
+    /// The global id of the output.
+    pub output: u32,
+    /// The index of the bar in `config::Config::bars`.
+    pub bar: usize,
+}
+
 pub struct WaylandContext {
-    pub surfaces: Rc<RefCell<Vec<(u32, Surface)>>>,
+    pub surfaces: Rc<RefCell<HashMap<SurfaceId, Surface>>>,
+    /// The configuration from which the configuration of each surface is resolved.
+    config: Rc<RefCell<Rc<config::Config>>>,
+    /// Creates, reconfigures or drops the surfaces of an output to match the configuration.
+    output_handler: Rc<dyn Fn(wl_output::WlOutput, &OutputInfo)>,
     display: Display,
     env: Environment<Env>,
     output_listener_handle: OutputStatusListener,
 }
 
 impl WaylandContext {
-    pub fn new() -> Result<UnhandledWaylandContext> {
-        let (env, display, queue) =
-            new_default_environment!(Env, fields = [layer_shell: SimpleGlobal::new(), ])
-                .expect("Initial roundtrip failed!");
+    pub fn new(config: Rc<config::Config>, modules: Modules) -> Result<UnhandledWaylandContext> {
+        let (env, display, queue) = new_environment()?;
 
-        let surfaces = Rc::new(RefCell::new(Vec::new()));
+        let surfaces = Rc::new(RefCell::new(HashMap::new()));
+        let config = Rc::new(RefCell::new(config));
         let layer_shell = env.require_global::<zwlr_layer_shell_v1::ZwlrLayerShellV1>();
+        let fractional_scaling = match (env.get_global(), env.get_global()) {
+            (Some(viewporter), Some(manager)) => Some(FractionalScaling { viewporter, manager }),
+            _ => None,
+        };
 
         // Create Rc handles for the output handler
         let env_handle = env.clone();
         let surfaces_handle = surfaces.clone();
+        let config_handle = config.clone();
+        // Listeners that follow changes to the scale of each output, which are not
+        // reported to the status listener. They are removed again when dropped.
+        let scale_listeners = RefCell::new(HashMap::<u32, OutputListener>::new());
+
+        let output_handler = Rc::new(move |output: wl_output::WlOutput, info: &OutputInfo| {
+            let mut surfaces = surfaces_handle.borrow_mut();
 
-        let output_handler = move |output: wl_output::WlOutput, info: &OutputInfo| {
             if info.obsolete {
                 // an output has been removed, release it
-                surfaces_handle.borrow_mut().retain(|(i, _)| *i != info.id);
+                surfaces.retain(|id: &SurfaceId, _| id.output != info.id);
+                scale_listeners.borrow_mut().remove(&info.id);
                 output.release();
-            } else {
-                // an output has been created, construct a surface for it
+                return;
+            }
+
+            if let Entry::Vacant(entry) = scale_listeners.borrow_mut().entry(info.id) {
+                let surfaces = Rc::clone(&surfaces_handle);
+                let listener = add_output_listener(&output, move |_, info, _| {
+                    if info.obsolete {
+                        return;
+                    }
+                    for (id, surface) in surfaces.borrow_mut().iter_mut() {
+                        if id.output == info.id {
+                            surface.set_output_scale(info.scale_factor);
+                        }
+                    }
+                });
+                if let Some(listener) = listener {
+                    entry.insert(listener);
+                }
+            }
+
+            let config = config_handle.borrow();
+            let theme = Rc::new(config.theme.clone());
+
+            // drop the surfaces of bars that no longer exist
+            surfaces.retain(|id, _| id.output != info.id || id.bar < config.bars.len());
+
+            for (bar, bar_config) in config.bars.iter().enumerate() {
+                let id = SurfaceId { output: info.id, bar };
+                let resolved = match bar_config.for_output(&info.name, &info.make, &info.model) {
+                    Some(resolved) => resolved,
+                    None => {
+                        // the bar is disabled on the output
+                        surfaces.remove(&id);
+                        continue;
+                    }
+                };
+
+                if let Some(surface) = surfaces.get_mut(&id) {
+                    surface.set_output_scale(info.scale_factor);
+                    if surface.can_reconfigure(&resolved) {
+                        if *surface.config() != resolved || *surface.theme() != *theme {
+                            surface.reconfigure(Rc::new(resolved), theme.clone());
+                        }
+                        continue;
+                    }
+                }
+
+                // the surface does not exist yet or has to be recreated, construct it
                 let surface = env_handle.create_surface().detach();
                 let pool = env_handle.create_auto_pool().expect("Failed to create a memory pool!");
-                (*surfaces_handle.borrow_mut())
-                    .push((info.id, Surface::new(&output, surface, &layer_shell.clone(), pool)));
+                let surface = Surface::new(
+                    &output,
+                    surface,
+                    &layer_shell.clone(),
+                    pool,
+                    SurfaceOptions {
+                        config: Rc::new(resolved),
+                        theme: theme.clone(),
+                        modules: modules.clone(),
+                        output_scale: info.scale_factor,
+                        fractional_scaling: fractional_scaling.as_ref(),
+                    },
+                );
+                surfaces.insert(id, surface);
             }
-        };
+        });
 
         // Process currently existing outputs
         for output in env.get_all_outputs() {
@@ -200,12 +934,15 @@ impl WaylandContext {
             }
         }
 
+        let listener_handler = output_handler.clone();
         let output_listener_handle
-            = env.listen_for_outputs(move |output, info, _| output_handler(output, info));
+            = env.listen_for_outputs(move |output, info, _| listener_handler(output, info));
 
         Ok(UnhandledWaylandContext(
             WaylandContext {
                 surfaces,
+                config,
+                output_handler,
                 env,
                 output_listener_handle,
                 display,
@@ -214,6 +951,20 @@ impl WaylandContext {
         ))
     }
 
+    /// Applies a new configuration to the surfaces of all outputs,
+    /// as well as to those created for outputs discovered later on.
+    pub fn reconfigure(&self, config: Rc<config::Config>) {
+        *self.config.borrow_mut() = config;
+
+        for output in self.env.get_all_outputs() {
+            if let Some(info) = with_output_info(&output, Clone::clone) {
+                if !info.obsolete {
+                    (self.output_handler)(output, &info);
+                }
+            }
+        }
+    }
+
     pub fn flush_display(&self) -> Result<()> {
         self.display.flush().map_err(|err| err.into())
     }
@@ -0,0 +1,401 @@
+//! The container widget, which arranges child widgets along a row or column
+//! within a background and border of its own, like a box of a flexbox layout.
+//!
+//! ```toml
+//! [[bar.right]]
+//! widget = "container"
+//! direction = "Row"
+//! gap = 6
+//! inset = 4
+//! background = "#333333"
+//! border = { width = 1, color = "#888888", radius = 10 }
+//!
+//! [[bar.right.children]]
+//! widget = "text"
+//! format = "BAT {battery.charge}%"
+//! grow = 1
+//! ```
+//!
+//! Besides their own options, children accept `min_width`, `max_width`, `padding`, `style`, as well as
+//! `grow` and `shrink`, which distribute the free or missing space of the container among them.
+//! Styles of children apply on top of the style of the container.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use log::warn;
+use piet::{Color, RenderContext};
+use piet::kurbo::{Rect, RoundedRect, Size};
+use piet_common::CairoRenderContext;
+use serde::Deserialize;
+use crate::config::{self, Placement, WidgetConfig};
+use crate::error::{Error, Kind, Result, Severity};
+use crate::modules::{ModuleRegistry, Widget, WidgetContext};
+use crate::theme::Theme;
+use crate::widgets;
+
+/// The direction along which the children of a container are placed.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+enum Direction {
+    Row,
+    Column,
+}
+
+impl Direction {
+    /// Splits a size into its length along and across the direction.
+    fn split(self, size: Size) -> (f64, f64) {
+        match self {
+            Direction::Row => (size.width, size.height),
+            Direction::Column => (size.height, size.width),
+        }
+    }
+
+    /// The rectangle at the given offsets along and across the direction, relative to `origin`.
+    fn rect(self, origin: Rect, main: (f64, f64), cross: (f64, f64)) -> Rect {
+        match self {
+            Direction::Row => Rect::new(origin.x0 + main.0, origin.y0 + cross.0, origin.x0 + main.1, origin.y0 + cross.1),
+            Direction::Column => Rect::new(origin.x0 + cross.0, origin.y0 + main.0, origin.x0 + cross.1, origin.y0 + main.1),
+        }
+    }
+}
+
+/// How children are placed along the direction of the container, when they do not fill it.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+enum Justify {
+    Start,
+    Center,
+    End,
+    SpaceBetween,
+}
+
+/// How children are placed across the direction of the container.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+enum Align {
+    Start,
+    Center,
+    End,
+    /// Children take up the entire space across the container.
+    Stretch,
+}
+
+#[derive(Deserialize, Debug, Default, Copy, Clone)]
+#[serde(default, deny_unknown_fields)]
+struct Border {
+    width: f64,
+    /// Defaults to the foreground color of the theme.
+    color: Option<config::Color>,
+    /// The radius of the corners, which applies to the background as well.
+    /// Defaults to the border radius of the theme.
+    radius: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ContainerOptions {
+    direction: Direction,
+    /// The space between adjacent children.
+    gap: f64,
+    /// The space between the border and the children.
+    inset: f64,
+    justify: Justify,
+    align: Align,
+    background: Option<config::Color>,
+    border: Border,
+    children: Vec<WidgetConfig>,
+}
+
+impl Default for ContainerOptions {
+    fn default() -> Self {
+        ContainerOptions {
+            direction: Direction::Row,
+            gap: 0.0,
+            inset: 0.0,
+            justify: Justify::Start,
+            align: Align::Center,
+            background: None,
+            border: Border::default(),
+            children: Vec::new(),
+        }
+    }
+}
+
+struct Child {
+    instance: widgets::Instance,
+    placement: Placement,
+    style: Option<config::StyleRef>,
+    /// The theme of the container with the style of the child applied,
+    /// resolved once the theme of the container is first known.
+    theme: RefCell<Option<Rc<Theme>>>,
+}
+
+impl Child {
+    fn new(instance: widgets::Instance, config: &WidgetConfig) -> Self {
+        Child {
+            instance,
+            placement: config.placement,
+            style: config.style.clone(),
+            theme: RefCell::new(None),
+        }
+    }
+
+    /// The theme of the child. If its style cannot be resolved,
+    /// the child falls back to the theme of the container.
+    fn theme(&self, ctx: &WidgetContext) -> Rc<Theme> {
+        let mut theme = self.theme.borrow_mut();
+        let theme = theme.get_or_insert_with(|| {
+            let style = self.style.as_ref().map(|style| ctx.theme.with_style(style));
+            Rc::new(match style {
+                Some(Ok(styled)) => styled,
+                Some(Err(err)) => {
+                    warn!("Unable to apply the style of a child widget: {}", err);
+                    ctx.theme.clone()
+                }
+                None => ctx.theme.clone(),
+            })
+        });
+        Rc::clone(theme)
+    }
+
+    fn context<'a>(&self, ctx: &WidgetContext<'a>, theme: &'a Theme) -> WidgetContext<'a> {
+        WidgetContext {
+            module: self.instance.module.and_then(|name| ctx.registry.get(name)),
+            registry: ctx.registry,
+            theme,
+        }
+    }
+
+    fn padding(&self, theme: &Theme) -> f64 {
+        self.placement.padding.unwrap_or(theme.padding)
+    }
+
+    /// The size of the child as `(main, cross)`, with its length along the container
+    /// including its padding and limited to its minimum and maximum width.
+    fn measure(&self, rc: &mut CairoRenderContext, direction: Direction, ctx: &WidgetContext) -> Result<(f64, f64)> {
+        let theme = self.theme(ctx);
+        let size = self.instance.widget.compute_dimensions(rc, &self.context(ctx, &theme))?;
+        let (main, cross) = direction.split(size);
+        let main = main + 2.0 * self.padding(&theme);
+        let main = self.placement.min_width.map_or(main, |min| main.max(min));
+        Ok((self.placement.max_width.map_or(main, |max| main.min(max)), cross))
+    }
+}
+
+/// A widget arranging its children along a row or column.
+#[derive(Default)]
+pub struct ContainerWidget {
+    options: ContainerOptions,
+    children: Vec<Child>,
+}
+
+impl ContainerWidget {
+    /// The space between the edges of the container and its children.
+    fn frame(&self) -> f64 {
+        self.options.border.width + self.options.inset
+    }
+
+    /// Distributes the free space, or the lack thereof, of the container among the children,
+    /// returning their lengths along the container.
+    fn distribute(&self, lengths: &[f64], available: f64) -> Vec<f64> {
+        let placements = self.children.iter().map(|child| child.placement).collect::<Vec<_>>();
+        distribute(&placements, lengths, self.options.gap, available)
+    }
+}
+
+/// Grows or shrinks the given lengths such that, along with the gaps between them, they fill
+/// the available space. Lengths are kept between their minimum and maximum width, with the
+/// space that a clamped length leaves over going to the remaining lengths, as in a flexbox.
+fn distribute(placements: &[Placement], lengths: &[f64], gap: f64, available: f64) -> Vec<f64> {
+    let gaps = gap * lengths.len().saturating_sub(1) as f64;
+    let growing = available - gaps - lengths.iter().sum::<f64>() >= 0.0;
+
+    let weights = placements.iter()
+        .zip(lengths)
+        .map(|(placement, length)| if growing { placement.grow } else { placement.shrink * length })
+        .collect::<Vec<_>>();
+
+    let mut result = lengths.to_vec();
+    let mut frozen = weights.iter().map(|weight| *weight <= 0.0).collect::<Vec<_>>();
+
+    loop {
+        let used = (0..lengths.len())
+            .map(|i| if frozen[i] { result[i] } else { lengths[i] })
+            .sum::<f64>();
+        let free = available - gaps - used;
+        let total_weight = (0..lengths.len())
+            .filter(|i| !frozen[*i])
+            .map(|i| weights[i])
+            .sum::<f64>();
+
+        if total_weight <= 0.0 {
+            return result;
+        }
+
+        let mut clamped = false;
+        for i in 0..lengths.len() {
+            if frozen[i] {
+                continue;
+            }
+
+            let length = lengths[i] + free * weights[i] / total_weight;
+            let min = placements[i].min_width.unwrap_or(0.0);
+            let max = placements[i].max_width.unwrap_or(f64::INFINITY);
+
+            result[i] = length.min(max).max(min);
+            if result[i] != length {
+                frozen[i] = true;
+                clamped = true;
+            }
+        }
+
+        if !clamped {
+            return result;
+        }
+    }
+}
+
+impl Widget for ContainerWidget {
+    /// Configures the container along with its children. Children that fail to be configured
+    /// are left out, which is reported as a warning such that the container itself remains.
+    fn configure(&mut self, options: toml::Value, registry: &ModuleRegistry) -> Result<()> {
+        let mut options: ContainerOptions = options.try_into()?;
+
+        let mut failures = Vec::new();
+        self.children = std::mem::take(&mut options.children).iter()
+            .filter_map(|config| match widgets::build(config, registry) {
+                Ok(instance) => Some(Child::new(instance, config)),
+                Err(err) => {
+                    failures.push(format!("unable to create child `{}`: {}", config, err));
+                    None
+                }
+            })
+            .collect();
+        self.options = options;
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::new(Kind::ModuleError(Severity::Warning)).with_msg(failures.join("; ")))
+        }
+    }
+
+    fn depends_on(&self, module: &str) -> bool {
+        self.children.iter().any(|child| {
+            child.instance.module == Some(module) || child.instance.widget.depends_on(module)
+        })
+    }
+
+    fn compute_dimensions(&self, rc: &mut CairoRenderContext, ctx: &WidgetContext) -> Result<Size> {
+        let direction = self.options.direction;
+        let (mut main, mut cross) = (0.0, 0.0f64);
+        for child in &self.children {
+            let (child_main, child_cross) = child.measure(rc, direction, ctx)?;
+            main += child_main;
+            cross = cross.max(child_cross);
+        }
+        main += self.options.gap * self.children.len().saturating_sub(1) as f64;
+
+        let frame = 2.0 * self.frame();
+        Ok(match direction {
+            Direction::Row => Size::new(main + frame, cross + frame),
+            Direction::Column => Size::new(cross + frame, main + frame),
+        })
+    }
+
+    fn draw(&self, rc: &mut CairoRenderContext, rect: Rect, ctx: &WidgetContext) -> Result<()> {
+        let border = self.options.border;
+        let radius = border.radius.unwrap_or(ctx.theme.border_radius);
+        let outline = RoundedRect::from_rect(rect.inset(-border.width / 2.0), radius);
+        if let Some(background) = self.options.background {
+            rc.fill(outline, &Color::from(background));
+        }
+        if border.width > 0.0 {
+            let color = border.color.map_or_else(|| ctx.theme.foreground.clone(), Color::from);
+            rc.stroke(outline, &color, border.width);
+        }
+
+        let content = rect.inset(-self.frame());
+        let direction = self.options.direction;
+        let (available, cross_length) = direction.split(content.size());
+
+        let sizes = self.children.iter()
+            .map(|child| child.measure(rc, direction, ctx))
+            .collect::<Result<Vec<_>>>()?;
+        let lengths = self.distribute(&sizes.iter().map(|(main, _)| *main).collect::<Vec<_>>(), available);
+
+        let used = lengths.iter().sum::<f64>() + self.options.gap * lengths.len().saturating_sub(1) as f64;
+        let free = (available - used).max(0.0);
+        let (mut offset, extra_gap) = match self.options.justify {
+            Justify::Start => (0.0, 0.0),
+            Justify::Center => (free / 2.0, 0.0),
+            Justify::End => (free, 0.0),
+            Justify::SpaceBetween if lengths.len() > 1 => (0.0, free / (lengths.len() - 1) as f64),
+            Justify::SpaceBetween => (0.0, 0.0),
+        };
+
+        for ((child, (_, cross)), length) in self.children.iter().zip(&sizes).zip(lengths) {
+            let cross = cross.min(cross_length);
+            let cross_offset = match self.options.align {
+                Align::Start | Align::Stretch => 0.0,
+                Align::Center => (cross_length - cross) / 2.0,
+                Align::End => cross_length - cross,
+            };
+            let cross_end = match self.options.align {
+                Align::Stretch => cross_length,
+                _ => cross_offset + cross,
+            };
+
+            let theme = child.theme(ctx);
+            let padding = child.padding(&theme);
+            let child_rect = direction.rect(content, (offset + padding, offset + length - padding), (cross_offset, cross_end));
+
+            rc.save()?;
+            rc.clip(child_rect);
+            let result = child.instance.widget.draw(rc, child_rect, &child.context(ctx, &theme));
+            rc.restore()?;
+            result?;
+
+            offset += length + self.options.gap + extra_gap;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placement(grow: f64, min_width: Option<f64>, max_width: Option<f64>) -> Placement {
+        Placement { grow, min_width, max_width, ..Placement::default() }
+    }
+
+    #[test]
+    fn free_space_is_shared_by_growing_children() {
+        let placements = [placement(1.0, None, None), placement(3.0, None, None)];
+        assert_eq!(distribute(&placements, &[10.0, 10.0], 0.0, 100.0), vec![30.0, 70.0]);
+        assert_eq!(distribute(&placements, &[10.0, 10.0], 20.0, 100.0), vec![25.0, 55.0]);
+
+        let placements = [placement(0.0, None, None), placement(0.0, None, None)];
+        assert_eq!(distribute(&placements, &[10.0, 10.0], 0.0, 100.0), vec![10.0, 10.0]);
+    }
+
+    #[test]
+    fn growing_children_stop_at_their_max_width() {
+        let placements = [placement(1.0, None, Some(40.0)), placement(1.0, None, None)];
+        assert_eq!(distribute(&placements, &[10.0, 10.0], 0.0, 100.0), vec![40.0, 60.0]);
+
+        let placements = [placement(1.0, None, Some(40.0)), placement(1.0, None, Some(30.0))];
+        assert_eq!(distribute(&placements, &[10.0, 10.0], 0.0, 100.0), vec![40.0, 30.0]);
+    }
+
+    #[test]
+    fn shrinking_children_stop_at_their_min_width() {
+        let placements = [placement(0.0, None, None), placement(0.0, None, None)];
+        assert_eq!(distribute(&placements, &[60.0, 40.0], 0.0, 50.0), vec![30.0, 20.0]);
+
+        let placements = [placement(0.0, Some(40.0), None), placement(0.0, None, None)];
+        assert_eq!(distribute(&placements, &[60.0, 40.0], 0.0, 50.0), vec![40.0, 10.0]);
+
+        let placements = [Placement { shrink: 0.0, ..Placement::default() }, placement(0.0, None, None)];
+        assert_eq!(distribute(&placements, &[60.0, 40.0], 0.0, 70.0), vec![60.0, 10.0]);
+    }
+}
@@ -0,0 +1,473 @@
+//! Format strings, which interpolate the values that modules expose
+//! through `Module::write`.
+//!
+//! Every `{module.field}` in a format string is replaced by the value of the field,
+//! while literal braces are written as `{{` and `}}`. A placeholder may further specify
+//! how the value is formatted, using a small subset of the syntax of `std::fmt`:
+//!
+//! ```text
+//! {module.field:[[fill]align][width][.precision][B]|"fallback"}
+//! ```
+//!
+//! - `align` is one of `<`, `^` or `>`, padding the value on the right, both sides or
+//!   the left respectively with `fill`, which defaults to a space, up to `width` characters.
+//! - `precision` gives the number of decimals of numeric values, whereas other values
+//!   are truncated to that many characters.
+//! - Neither `width` nor `precision` may exceed 1024.
+//! - `B` displays numeric values as a byte size, such as `1.5 GiB`.
+//! - `fallback` is displayed in place of the value when the latter is empty.
+//!
+//! For example, `{battery.charge:>3}` right-aligns the charge and
+//! `{core:sway.focused_view_name|"desktop"}` displays `desktop` when no view is focused.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use serde::{de, Deserialize, Deserializer};
+use crate::config::FieldRef;
+use crate::error::Result;
+use crate::modules::ModuleRegistry;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+impl Align {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '<' => Some(Align::Left),
+            '^' => Some(Align::Center),
+            '>' => Some(Align::Right),
+            _ => None,
+        }
+    }
+}
+
+/// Describes how the value of a placeholder is formatted,
+/// written after a `:` following the field.
+#[derive(Debug, Clone, PartialEq)]
+struct Spec {
+    fill: char,
+    align: Align,
+    width: Option<usize>,
+    precision: Option<usize>,
+    /// Whether numeric values are displayed as a byte size.
+    bytes: bool,
+}
+
+impl Default for Spec {
+    fn default() -> Self {
+        Spec { fill: ' ', align: Align::Left, width: None, precision: None, bytes: false }
+    }
+}
+
+impl Spec {
+    /// Writes the formatted value to `dst`.
+    fn apply(&self, value: &str, dst: &mut String) {
+        let value = match (self.bytes, self.precision, value.trim().parse::<f64>()) {
+            (true, precision, Ok(number)) => format_bytes(number, precision),
+            (false, Some(precision), Ok(number)) => format!("{:.*}", precision, number),
+            (false, Some(precision), Err(_)) => value.chars().take(precision).collect(),
+            _ => value.to_owned(),
+        };
+
+        let padding = self.width.unwrap_or(0).saturating_sub(value.chars().count());
+        let (before, after) = match self.align {
+            Align::Left => (0, padding),
+            Align::Center => (padding / 2, padding - padding / 2),
+            Align::Right => (padding, 0),
+        };
+
+        dst.extend(std::iter::repeat(self.fill).take(before));
+        dst.push_str(&value);
+        dst.extend(std::iter::repeat(self.fill).take(after));
+    }
+}
+
+impl FromStr for Spec {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut spec = Spec::default();
+        let mut rest = s;
+
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(fill), Some(align)) if Align::from_char(align).is_some() => {
+                spec.fill = fill;
+                spec.align = Align::from_char(align).unwrap();
+                rest = &rest[fill.len_utf8() + 1..];
+            }
+            (Some(align), _) if Align::from_char(align).is_some() => {
+                spec.align = Align::from_char(align).unwrap();
+                rest = &rest[1..];
+            }
+            _ => {}
+        }
+
+        let (width, after) = split_number(rest, "width")?;
+        spec.width = width;
+        rest = after;
+
+        if let Some(after) = rest.strip_prefix('.') {
+            let (precision, after) = split_number(after, "precision")?;
+            spec.precision = Some(precision.ok_or_else(|| format!("missing precision in `{}`", s))?);
+            rest = after;
+        }
+
+        if let Some(after) = rest.strip_prefix('B') {
+            spec.bytes = true;
+            rest = after;
+        }
+
+        if rest.is_empty() {
+            Ok(spec)
+        } else {
+            Err(format!("invalid format spec `{}`", s))
+        }
+    }
+}
+
+/// The largest width or precision that a placeholder may specify, such that
+/// a mistyped number cannot fill every render with millions of characters.
+const MAX_NUMBER: usize = 1024;
+
+/// Splits the leading decimal number off of the given string, failing if it exceeds
+/// `MAX_NUMBER`. The number is referred to as `name` in the error.
+fn split_number<'a>(s: &'a str, name: &str) -> std::result::Result<(Option<usize>, &'a str), String> {
+    let len = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    if len == 0 {
+        return Ok((None, s));
+    }
+
+    match s[..len].parse() {
+        Ok(number) if number <= MAX_NUMBER => Ok((Some(number), &s[len..])),
+        _ => Err(format!("{} `{}` exceeds the maximum of {}", name, &s[..len], MAX_NUMBER)),
+    }
+}
+
+/// Formats a number of bytes using the largest binary unit that keeps it above 1.
+/// Unless a precision is given, bytes are displayed without and other units with a single decimal.
+fn format_bytes(bytes: f64, precision: Option<usize>) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    let mut value = bytes;
+    let mut unit = 0;
+    while value.abs() >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    let precision = precision.unwrap_or(if unit == 0 { 0 } else { 1 });
+    format!("{:.*} {}", precision, value, UNITS[unit])
+}
+
+/// A field to be interpolated into a format string.
+#[derive(Debug, Clone, PartialEq)]
+struct Placeholder {
+    field: FieldRef,
+    spec: Spec,
+    fallback: Option<String>,
+}
+
+impl Placeholder {
+    /// Parses a placeholder from the text following its opening brace,
+    /// returning it along with the text following its closing brace.
+    fn parse(src: &str) -> std::result::Result<(Self, &str), String> {
+        let unclosed = || "unclosed `{`".to_owned();
+
+        // Module names may contain colons, so a colon only ends the field past the dot,
+        // whereas a fallback or the closing brace ends it regardless
+        let dot = src.find(['.', '|', '}']).ok_or_else(unclosed)?;
+        let end = if src[dot..].starts_with('.') {
+            src[dot..].find([':', '|', '}']).map(|i| dot + i).ok_or_else(unclosed)?
+        } else {
+            dot
+        };
+        let field = src[..end].trim().parse::<FieldRef>()?;
+        let mut rest = &src[end..];
+
+        let mut spec = Spec::default();
+        if let Some(after) = rest.strip_prefix(':') {
+            let len = after.find(['|', '}']).ok_or_else(unclosed)?;
+            spec = after[..len].parse()?;
+            rest = &after[len..];
+        }
+
+        let mut fallback = None;
+        if let Some(after) = rest.strip_prefix('|') {
+            let (text, after) = parse_quoted(after.trim_start())?;
+            fallback = Some(text);
+            rest = after.trim_start();
+        }
+
+        match rest.strip_prefix('}') {
+            Some(rest) => Ok((Placeholder { field, spec, fallback }, rest)),
+            None => Err(format!("expected `}}` after the placeholder of `{}`", field)),
+        }
+    }
+
+    /// Writes the formatted value of the field to `dst`.
+    /// Fields of modules that have since been unloaded are treated as empty.
+    fn write(&self, registry: &ModuleRegistry, dst: &mut String) -> Result<()> {
+        let mut value = String::new();
+        if let Some(module) = registry.get(&self.field.module) {
+            module.write(&self.field.field, &mut value)?;
+        }
+
+        match &self.fallback {
+            Some(fallback) if value.is_empty() => self.spec.apply(fallback, dst),
+            _ => self.spec.apply(&value, dst),
+        }
+        Ok(())
+    }
+}
+
+/// Parses a double-quoted string in which `\"` and `\\` are escaped,
+/// returning it along with the text following the closing quote.
+fn parse_quoted(src: &str) -> std::result::Result<(String, &str), String> {
+    let body = src.strip_prefix('"')
+        .ok_or_else(|| "fallbacks must be quoted, as in `|\"text\"`".to_owned())?;
+
+    let mut text = String::new();
+    let mut escaped = false;
+    for (i, c) in body.char_indices() {
+        match c {
+            c if escaped => {
+                text.push(c);
+                escaped = false;
+            }
+            '\\' => escaped = true,
+            '"' => return Ok((text, &body[i + 1..])),
+            c => text.push(c),
+        }
+    }
+
+    Err("unclosed `\"`".to_owned())
+}
+
+/// A piece of a format string.
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Field(Placeholder),
+}
+
+/// A parsed format string.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Format {
+    src: String,
+    segments: Vec<Segment>,
+}
+
+impl Format {
+    /// A format string consisting of the given text only, without any placeholders.
+    pub fn literal(text: &str) -> Self {
+        Format {
+            src: text.replace('{', "{{").replace('}', "}}"),
+            segments: vec![Segment::Literal(text.to_owned())],
+        }
+    }
+
+    /// The fields referenced by the format string, in order of appearance.
+    pub fn fields(&self) -> impl Iterator<Item=&FieldRef> {
+        self.segments.iter().filter_map(|segment| match segment {
+            Segment::Field(placeholder) => Some(&placeholder.field),
+            Segment::Literal(_) => None,
+        })
+    }
+
+    /// Writes the format string to `dst`, interpolating the current values of its fields.
+    pub fn write(&self, registry: &ModuleRegistry, dst: &mut String) -> Result<()> {
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(literal) => dst.push_str(literal),
+                Segment::Field(placeholder) => placeholder.write(registry, dst)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut rest = s;
+
+        while let Some(c) = rest.chars().next() {
+            rest = &rest[c.len_utf8()..];
+            match c {
+                '{' if rest.starts_with('{') => {
+                    rest = &rest[1..];
+                    literal.push('{');
+                }
+                '}' if rest.starts_with('}') => {
+                    rest = &rest[1..];
+                    literal.push('}');
+                }
+                '{' => {
+                    let (placeholder, after) = Placeholder::parse(rest)
+                        .map_err(|err| format!("{} in `{}`", err, s))?;
+                    rest = after;
+
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Field(placeholder));
+                }
+                '}' => return Err(format!("unmatched `}}` in `{}`, write `}}}}` for a literal brace", s)),
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Format { src: s.to_owned(), segments })
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.src)
+    }
+}
+
+impl<'de> Deserialize<'de> for Format {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let src = String::deserialize(deserializer)?;
+        src.parse().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::{FieldInfo, Module, Named};
+
+    struct Stub;
+
+    impl Named for Stub {
+        const NAME: &'static str = "core:stub";
+    }
+
+    impl Module for Stub {
+        fn fields(&self) -> &'static [FieldInfo] {
+            &[]
+        }
+
+        fn write(&self, field: &str, dst: &mut String) -> Result<bool> {
+            match field {
+                "name" => dst.push_str("owl"),
+                "empty" => {}
+                "number" => dst.push_str("3.14159"),
+                "bytes" => dst.push_str("1572864"),
+                _ => return Ok(false),
+            }
+            Ok(true)
+        }
+    }
+
+    fn render(src: &str) -> String {
+        let mut registry = ModuleRegistry::new();
+        registry.register(Stub).unwrap();
+
+        let mut dst = String::new();
+        src.parse::<Format>().unwrap().write(&registry, &mut dst).unwrap();
+        dst
+    }
+
+    fn parse_err(src: &str) -> String {
+        src.parse::<Format>().unwrap_err()
+    }
+
+    #[test]
+    fn escaped_braces_are_literal() {
+        let format = "a {{b}} c".parse::<Format>().unwrap();
+        assert_eq!(format.segments, vec![Segment::Literal("a {b} c".to_owned())]);
+        assert_eq!(render("{{{core:stub.name}}}"), "{owl}");
+    }
+
+    #[test]
+    fn fields_are_listed_in_order() {
+        let format = "{core:stub.name} and {battery.charge:>3}".parse::<Format>().unwrap();
+        let fields = format.fields().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(fields, ["core:stub.name", "battery.charge"]);
+    }
+
+    #[test]
+    fn module_names_may_contain_colons() {
+        assert_eq!(render("{core:stub.name}"), "owl");
+        assert_eq!(render("{core:stub.number:.2}"), "3.14");
+    }
+
+    #[test]
+    fn fill_align_and_width() {
+        assert_eq!(render("[{core:stub.name:5}]"), "[owl  ]");
+        assert_eq!(render("[{core:stub.name:>5}]"), "[  owl]");
+        assert_eq!(render("[{core:stub.name:^6}]"), "[ owl  ]");
+        assert_eq!(render("[{core:stub.name:*^7}]"), "[**owl**]");
+        assert_eq!(render("[{core:stub.name:>2}]"), "[owl]");
+    }
+
+    #[test]
+    fn precision_rounds_numbers_and_truncates_text() {
+        assert_eq!(render("{core:stub.number:.0}"), "3");
+        assert_eq!(render("{core:stub.number:>6.1}"), "   3.1");
+        assert_eq!(render("{core:stub.name:.2}"), "ow");
+    }
+
+    #[test]
+    fn byte_sizes() {
+        assert_eq!(render("{core:stub.bytes:B}"), "1.5 MiB");
+        assert_eq!(render("{core:stub.bytes:.2B}"), "1.50 MiB");
+        assert_eq!(render("{core:stub.name:B}"), "owl");
+        assert_eq!(format_bytes(512.0, None), "512 B");
+        assert_eq!(format_bytes(1024.0f64.powi(6), None), "1024.0 PiB");
+    }
+
+    #[test]
+    fn fallbacks_replace_empty_values() {
+        assert_eq!(render(r#"{core:stub.empty|"none"}"#), "none");
+        assert_eq!(render(r#"{core:stub.name|"none"}"#), "owl");
+        assert_eq!(render(r#"{core:stub.empty:>6|"n/a"}"#), "   n/a");
+        assert_eq!(render(r#"{core:stub.empty | "a.b:c}" }"#), "a.b:c}");
+        assert_eq!(render(r#"{core:stub.empty|"say \"hi\" \\"}"#), r#"say "hi" \"#);
+    }
+
+    #[test]
+    fn unknown_modules_and_fields_are_empty() {
+        assert_eq!(render("[{gone.field}]"), "[]");
+        assert_eq!(render(r#"[{core:stub.missing|"-"}]"#), "[-]");
+    }
+
+    #[test]
+    fn literals_round_trip() {
+        let format = Format::literal("{x}");
+        assert_eq!(format.to_string(), "{{x}}");
+        assert_eq!(format.to_string().parse::<Format>().unwrap(), format);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse_err("{core:stub.name").starts_with("unclosed `{`"));
+        assert!(parse_err("{}").contains("is not of the form `module.field`"));
+        assert!(parse_err(r#"{a|"x.y"}"#).starts_with("`a` is not of the form `module.field`"));
+        assert!(parse_err("a } b").starts_with("unmatched `}`"));
+        assert!(parse_err("{a.b|x}").starts_with("fallbacks must be quoted"));
+        assert!(parse_err(r#"{a.b|"x}"#).starts_with("unclosed `\"`"));
+        assert!(parse_err(r#"{a.b|"x" y}"#).starts_with("expected `}` after the placeholder of `a.b`"));
+        assert!(parse_err("{a.b:.}").starts_with("missing precision"));
+        assert!(parse_err("{a.b:x}").starts_with("invalid format spec `x`"));
+        assert!(parse_err("{a.b:99999999999999999999}").starts_with("width `99999999999999999999` exceeds the maximum of 1024"));
+        assert!(parse_err("{a.b:4000000000}").starts_with("width `4000000000` exceeds the maximum"));
+        assert!(parse_err("{a.b:.1025}").starts_with("precision `1025` exceeds the maximum"));
+        assert!("{a.b:1024.1024}".parse::<Format>().is_ok());
+    }
+}
@@ -0,0 +1,63 @@
+//! Builtin widgets that are not tied to a single module,
+//! as well as the instantiation of widgets from their configuration.
+
+use log::warn;
+use crate::config::WidgetConfig;
+use crate::error::{Error, Kind, Result, Severity};
+use crate::modules::{ModuleRegistry, Widget};
+
+pub mod container;
+pub mod format;
+pub mod text;
+
+pub use container::ContainerWidget;
+pub use format::Format;
+pub use text::TextWidget;
+
+/// The builtin widgets, which are configured without a module prefix.
+pub const BUILTIN: &[&str] = &["container", "text"];
+
+/// A configured widget along with the module that registered it, `None` for builtin widgets.
+pub struct Instance {
+    pub widget: Box<dyn Widget>,
+    pub module: Option<&'static str>,
+}
+
+/// Instantiates and configures the widget described by the configuration.
+/// Warnings raised while configuring the widget are logged, whereas fatal errors are returned.
+pub fn build(config: &WidgetConfig, registry: &ModuleRegistry) -> Result<Instance> {
+    let (instance, warning) = build_checked(config, registry)?;
+    if let Some(warning) = warning {
+        warn!("Widget `{}`: {}", config.widget, warning);
+    }
+    Ok(instance)
+}
+
+/// Like `build`, but returns the warning raised while configuring the widget, if any,
+/// alongside the widget rather than logging it.
+pub fn build_checked(config: &WidgetConfig, registry: &ModuleRegistry) -> Result<(Instance, Option<Error>)> {
+    let (module, mut widget): (_, Box<dyn Widget>) = match config.widget.as_str() {
+        "container" => (None, Box::new(ContainerWidget::default())),
+        "text" => (None, Box::new(TextWidget::default())),
+        name => {
+            let (module, widget) = name.split_once('.')
+                .ok_or_else(|| Error::new(Kind::ConfigError)
+                    .with_msg(format!("unknown widget `{}`, module widgets are written as `module.widget`", name)))?;
+
+            let (module, widget) = registry.build_widget(module, widget)
+                .ok_or_else(|| Error::new(Kind::ConfigError)
+                    .with_msg(format!("unknown widget `{}`", name)))?;
+            (Some(module), widget)
+        }
+    };
+
+    let warning = match widget.configure(config.options(), registry) {
+        Ok(()) => None,
+        Err(err) => match err.severity() {
+            Severity::Warning => Some(err),
+            Severity::Fatal => return Err(err),
+        },
+    };
+
+    Ok((Instance { widget, module }, warning))
+}
@@ -0,0 +1,107 @@
+//! The text widget, which displays a format string interpolated with the values
+//! that modules expose through `Module::write`. See `widgets::format` for the syntax.
+//!
+//! Configured as `{ widget = "text", format = "BAT {battery.charge}%" }`,
+//! or simply as the format string itself.
+
+use serde::Deserialize;
+use piet::{RenderContext, Text, TextLayout, TextLayoutBuilder};
+use piet::kurbo::{Rect, Size};
+use piet_common::{CairoRenderContext, CairoTextLayout};
+use crate::error::{Error, Kind, Result};
+use crate::modules::{ModuleRegistry, Widget, WidgetContext};
+use crate::widgets::Format;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TextOptions {
+    format: Format,
+}
+
+/// A widget displaying a format string whose fields have been resolved
+/// against the modules of a `ModuleRegistry`.
+#[derive(Default)]
+pub struct TextWidget {
+    format: Format,
+}
+
+impl TextWidget {
+    /// Creates a text widget displaying the given text as is.
+    pub fn literal(text: &str) -> Self {
+        TextWidget { format: Format::literal(text) }
+    }
+
+    /// Fails if the format string references a module that is not known to the registry,
+    /// or a field that the module does not expose.
+    fn check(format: &Format, registry: &ModuleRegistry) -> Result<()> {
+        for field in format.fields() {
+            let fields = registry.fields(&field.module)
+                .ok_or_else(|| Error::new(Kind::ConfigError)
+                    .with_msg(format!("unknown module `{}`", field.module)))?;
+
+            if !fields.iter().any(|info| info.name == field.field) {
+                let available = fields.iter()
+                    .map(|info| info.name)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                return Err(Error::new(Kind::ConfigError).with_msg(format!(
+                    "module `{}` has no field `{}`, available fields are: {}",
+                    field.module, field.field, available,
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The text of the widget, using the current values of the referenced fields.
+    fn text(&self, ctx: &WidgetContext) -> Result<String> {
+        let mut text = String::new();
+        self.format.write(ctx.registry, &mut text)?;
+        Ok(text)
+    }
+
+    fn build_layout(rc: &mut CairoRenderContext, ctx: &WidgetContext, text: String) -> Result<CairoTextLayout> {
+        let theme = ctx.theme;
+        Ok(rc.text()
+            .new_text_layout(text)
+            .font(theme.font_family.clone(), theme.font_size)
+            .default_attribute(theme.font_weight)
+            .text_color(theme.foreground.clone())
+            .build()?)
+    }
+}
+
+impl Widget for TextWidget {
+    fn configure(&mut self, options: toml::Value, registry: &ModuleRegistry) -> Result<()> {
+        let options: TextOptions = options.try_into()?;
+        Self::check(&options.format, registry)?;
+        self.format = options.format;
+        Ok(())
+    }
+
+    fn depends_on(&self, module: &str) -> bool {
+        self.format.fields().any(|field| field.module == module)
+    }
+
+    fn compute_dimensions(&self, rc: &mut CairoRenderContext, ctx: &WidgetContext) -> Result<Size> {
+        Ok(Self::build_layout(rc, ctx, self.text(ctx)?)?.size())
+    }
+
+    /// Draws the text centered within `rect`. Text that does not fit is shortened and
+    /// ended with an ellipsis, as happens to widgets on bars that run out of space.
+    fn draw(&self, rc: &mut CairoRenderContext, rect: Rect, ctx: &WidgetContext) -> Result<()> {
+        let text = self.text(ctx)?;
+        let mut layout = Self::build_layout(rc, ctx, text.clone())?;
+
+        let mut chars = text.chars().collect::<Vec<_>>();
+        while layout.size().width > rect.width() && !chars.is_empty() {
+            chars.pop();
+            let shortened = chars.iter().collect::<String>() + "…";
+            layout = Self::build_layout(rc, ctx, shortened)?;
+        }
+
+        rc.draw_text(&layout, rect.center() - layout.size().to_vec2() / 2.0);
+        Ok(())
+    }
+}